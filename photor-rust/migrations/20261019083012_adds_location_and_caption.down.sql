@@ -0,0 +1,4 @@
+alter table photos drop column latitude;
+alter table photos drop column longitude;
+alter table photos drop column altitude;
+alter table photos drop column caption;
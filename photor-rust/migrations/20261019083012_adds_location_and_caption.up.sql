@@ -0,0 +1,4 @@
+alter table photos add column latitude real;
+alter table photos add column longitude real;
+alter table photos add column altitude real;
+alter table photos add column caption text;
@@ -0,0 +1,3 @@
+drop index photos_location_index;
+
+alter table photos drop column gps_direction;
@@ -0,0 +1,3 @@
+alter table photos add column gps_direction real;
+
+create index photos_location_index on photos(latitude, longitude);
@@ -0,0 +1 @@
+alter table photos drop column location_source;
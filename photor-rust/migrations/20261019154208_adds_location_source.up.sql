@@ -0,0 +1,4 @@
+-- where the location of the photo comes from: 'exif', 'takeout' (sidecar) or 'gpx' (geotag)
+alter table photos add column location_source text;
+
+update photos set location_source = 'exif' where latitude is not null;
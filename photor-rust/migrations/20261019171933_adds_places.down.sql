@@ -0,0 +1,8 @@
+drop index photos_city_index;
+drop index photos_region_index;
+drop index photos_country_index;
+
+alter table photos drop column city;
+alter table photos drop column region;
+alter table photos drop column country;
+alter table photos drop column country_code;
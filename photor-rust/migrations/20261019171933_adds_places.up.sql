@@ -0,0 +1,9 @@
+-- the place names found by reverse geocoding the location of the photos:
+alter table photos add column country_code text;
+alter table photos add column country text;
+alter table photos add column region text;
+alter table photos add column city text;
+
+create index photos_country_index on photos(country collate nocase);
+create index photos_region_index on photos(region collate nocase);
+create index photos_city_index on photos(city collate nocase);
@@ -0,0 +1,19 @@
+-- video specific metadata, one row per video file of the photos table:
+create table videos (
+  photo_id integer primary key not null,
+  duration_seconds real,
+  video_codec text,
+  audio_codec text,
+  frame_rate real,
+  -- bits per second:
+  bitrate integer,
+  -- clockwise, in degrees:
+  rotation integer,
+  -- the dimensions of the video as displayed, i.e. with the rotation applied:
+  display_width integer,
+  display_height integer,
+
+  foreign key (photo_id) references photos (id) on delete cascade
+);
+
+create index videos_duration_index on videos(duration_seconds);
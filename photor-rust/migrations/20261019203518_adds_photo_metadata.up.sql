@@ -0,0 +1,9 @@
+-- the complete exiftool output of each photo, as zlib compressed JSON. It's uncompressed in SQL
+-- with the photor_inflate() function registered by photor on its connections, like:
+--   select json_extract(photor_inflate(data), '$.Flash') from photo_metadata;
+create table photo_metadata (
+  photo_id integer primary key not null,
+  data blob not null,
+
+  foreign key (photo_id) references photos (id) on delete cascade
+);
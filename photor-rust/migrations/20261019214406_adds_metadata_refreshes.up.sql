@@ -0,0 +1,7 @@
+-- the progress of the `photor metadata refresh` runs, to resume them when interrupted. A run is
+-- identified by its options, and removed once completed.
+create table metadata_refreshes (
+  options text primary key not null,
+  last_photo_id integer not null,
+  updated_at text not null
+);
@@ -0,0 +1,7 @@
+drop index photos_focal_length_mm_index;
+drop index photos_exposure_seconds_index;
+
+alter table photos drop column focal_length_35mm;
+alter table photos drop column focal_length_mm;
+alter table photos drop column f_number;
+alter table photos drop column exposure_seconds;
@@ -0,0 +1,22 @@
+-- numeric values next to the display strings, for the range queries:
+alter table photos add column exposure_seconds real;
+alter table photos add column f_number real;
+alter table photos add column focal_length_mm real;
+alter table photos add column focal_length_35mm real;
+
+-- filled from the display strings for the photos already imported (like `1/100`, `0.3` or
+-- `28.0 mm`). The f-numbers and 35mm equivalent focal lengths need the files to be read again,
+-- with `photor metadata refresh --only-missing --field f_number --field focal_length_35mm`.
+update photos
+set exposure_seconds = case
+  when shutter_speed like '1/%' then 1.0 / nullif(cast(substr(shutter_speed, 3) as real), 0)
+  else nullif(cast(shutter_speed as real), 0)
+end
+where shutter_speed is not null;
+
+update photos
+set focal_length_mm = nullif(cast(focal_length as real), 0)
+where focal_length is not null;
+
+create index photos_exposure_seconds_index on photos(exposure_seconds);
+create index photos_focal_length_mm_index on photos(focal_length_mm);
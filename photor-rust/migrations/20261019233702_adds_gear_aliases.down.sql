@@ -0,0 +1,8 @@
+drop table gear_aliases;
+
+drop index photos_normalized_lens_index;
+drop index photos_normalized_camera_index;
+
+alter table photos drop column normalized_lens;
+alter table photos drop column normalized_model;
+alter table photos drop column normalized_make;
@@ -0,0 +1,19 @@
+-- the camera and lens names, normalized from the EXIF vendor strings (like `RICOH IMAGING
+-- COMPANY, LTD.` -> `Ricoh`) and the aliases below. They're set at the import, and for the photos
+-- already imported with `photor gear normalize`.
+alter table photos add column normalized_make text;
+alter table photos add column normalized_model text;
+alter table photos add column normalized_lens text;
+
+create index photos_normalized_camera_index on photos(normalized_make, normalized_model);
+create index photos_normalized_lens_index on photos(normalized_lens);
+
+-- the names given to the normalized makes, models and lenses, to merge the variants of a same
+-- gear:
+create table gear_aliases (
+  kind text not null check (kind in ('make', 'model', 'lens')),
+  value text not null,
+  name text not null,
+
+  primary key (kind, value)
+);
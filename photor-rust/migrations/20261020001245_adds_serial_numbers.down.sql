@@ -0,0 +1,5 @@
+drop index photos_camera_serial_index;
+
+alter table photos drop column shutter_count;
+alter table photos drop column lens_serial;
+alter table photos drop column camera_serial;
@@ -0,0 +1,6 @@
+alter table photos add column camera_serial text;
+alter table photos add column lens_serial text;
+-- the number of shutter actuations (or images) of the body, from the maker notes:
+alter table photos add column shutter_count integer;
+
+create index photos_camera_serial_index on photos(camera_serial);
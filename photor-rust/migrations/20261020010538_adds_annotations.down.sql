@@ -0,0 +1,9 @@
+drop table photo_tags;
+
+drop index photos_rating_index;
+
+alter table photos drop column color_label;
+alter table photos drop column rating;
+alter table photos drop column display_height;
+alter table photos drop column display_width;
+alter table photos drop column orientation;
@@ -0,0 +1,24 @@
+-- the EXIF orientation (1 to 8), and the dimensions of the photo as displayed, i.e. with the
+-- orientation applied:
+alter table photos add column orientation integer;
+alter table photos add column display_width integer;
+alter table photos add column display_height integer;
+-- the XMP rating: 1 to 5 stars, 0 for none and -1 for rejected:
+alter table photos add column rating integer;
+-- the XMP label, like `Red`:
+alter table photos add column color_label text;
+
+update photos set display_width = image_width, display_height = image_height;
+
+create index photos_rating_index on photos(rating);
+
+-- the keywords of the photos (XMP subject, IPTC keywords):
+create table photo_tags (
+  photo_id integer not null,
+  tag text not null,
+
+  foreign key (photo_id) references photos (id) on delete cascade,
+  unique (photo_id, tag)
+);
+
+create index photo_tags_tag_index on photo_tags(tag collate nocase);
@@ -0,0 +1,2 @@
+-- nothing to revert: the completed dates are valid for the previous versions too.
+select 1;
@@ -0,0 +1,4 @@
+-- the dates are now read as date-times: the ones saved without time (like the 1970-01-01
+-- fallback of the photos without date) get midnight.
+update photos set create_date = create_date || ' 00:00:00' where length(create_date) = 10;
+update photos set inserted_at = inserted_at || ' 00:00:00' where length(inserted_at) = 10;
@@ -0,0 +1,13 @@
+create trigger if not exists update_create_day_after_insert
+after insert on photos
+for each row
+begin
+   update photos set create_day = date(new.create_date) where rowid = new.rowid;
+end;
+
+create trigger if not exists update_create_day_after_update
+after update of create_date on photos
+for each row
+begin
+   update photos set create_day = date(new.create_date) where rowid = new.rowid;
+end;
@@ -0,0 +1,7 @@
+-- the web app drops the create_day triggers (its migrations 20250529000001 and 20250704000000)
+-- and sets create_day itself: photor does the same, so that both can share one database.
+drop trigger if exists set_create_day;
+drop trigger if exists update_create_day_after_insert;
+drop trigger if exists update_create_day_after_update;
+
+update photos set create_day = date(create_date) where create_day is null;
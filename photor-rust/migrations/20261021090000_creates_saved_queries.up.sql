@@ -0,0 +1,6 @@
+-- the queries selecting photos saved by name, to be reused with @name in the other queries:
+create table saved_queries (
+  name text primary key not null,
+  query text not null,
+  updated_at text not null
+);
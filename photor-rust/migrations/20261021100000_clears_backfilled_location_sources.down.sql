@@ -0,0 +1,2 @@
+update photos set location_source = 'exif'
+where location_source is null and latitude is not null;
@@ -0,0 +1,7 @@
+-- the photos located before the location sources were recorded were all labelled 'exif', though
+-- some locations came from Takeout sidecars: their source is unknown.
+update photos set location_source = null
+where location_source = 'exif'
+and inserted_at < (
+  select installed_on from _sqlx_migrations where version = 20261019154208
+);
@@ -0,0 +1,119 @@
+// The orientations as written by exiftool, in the order of their EXIF values (1 to 8).
+const ORIENTATIONS: [&str; 8] = [
+    "Horizontal (normal)",
+    "Mirror horizontal",
+    "Rotate 180",
+    "Mirror vertical",
+    "Mirror horizontal and rotate 270 CW",
+    "Rotate 90 CW",
+    "Mirror horizontal and rotate 90 CW",
+    "Rotate 270 CW",
+];
+
+// The descriptions written by some cameras in place of a caption.
+const PLACEHOLDER_CAPTIONS: &[&str] = &[
+    "OLYMPUS DIGITAL CAMERA",
+    "SONY DSC",
+    "DCIM\\100MEDIA",
+    "DIGITAL CAMERA",
+    "Default",
+];
+
+/// Converts an orientation as written by exiftool, like `Rotate 90 CW` or `6`, to its EXIF value.
+pub fn parse_orientation(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(orientation) = value.parse::<i64>() {
+        return Some(orientation).filter(|orientation| (1..=8).contains(orientation));
+    }
+
+    ORIENTATIONS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(value))
+        .map(|index| index as i64 + 1)
+}
+
+/// Returns the dimensions of a photo as displayed, the width and height being swapped for the
+/// orientations turning it by a quarter turn (5 to 8).
+pub fn display_dimensions(
+    width: Option<i64>,
+    height: Option<i64>,
+    orientation: Option<i64>,
+) -> (Option<i64>, Option<i64>) {
+    match orientation {
+        Some(5..=8) => (height, width),
+        _ => (width, height),
+    }
+}
+
+/// Parses an XMP rating: 0 to 5, or -1 for the rejected photos.
+pub fn parse_rating(value: &str) -> Option<i64> {
+    let rating = value.trim().parse::<f64>().ok()?.round() as i64;
+    Some(rating).filter(|rating| (-1..=5).contains(rating))
+}
+
+/// The caption, unless it's empty or a placeholder written by the camera.
+pub fn clean_caption(value: &str) -> Option<String> {
+    let caption = value.trim();
+    if caption.is_empty()
+        || PLACEHOLDER_CAPTIONS
+            .iter()
+            .any(|placeholder| placeholder.eq_ignore_ascii_case(caption))
+    {
+        return None;
+    }
+    Some(caption.to_string())
+}
+
+/// Merges keyword lists into tags: trimmed, without the empty ones and the duplicates (ignoring
+/// the case, the first spelling being kept).
+pub fn keywords_to_tags<'a, I>(keywords: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut tags: Vec<String> = Vec::new();
+    for keyword in keywords {
+        let keyword = keyword.trim();
+        if !keyword.is_empty() && !tags.iter().any(|tag| tag.eq_ignore_ascii_case(keyword)) {
+            tags.push(keyword.to_string());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_orientation() {
+        assert_eq!(parse_orientation("Horizontal (normal)"), Some(1));
+        assert_eq!(parse_orientation("Rotate 90 CW"), Some(6));
+        assert_eq!(parse_orientation("8"), Some(8));
+        assert_eq!(parse_orientation("Unknown (0)"), None);
+    }
+
+    #[test]
+    fn test_display_dimensions() {
+        assert_eq!(
+            display_dimensions(Some(6000), Some(4000), Some(6)),
+            (Some(4000), Some(6000))
+        );
+        assert_eq!(
+            display_dimensions(Some(6000), Some(4000), Some(3)),
+            (Some(6000), Some(4000))
+        );
+    }
+
+    #[test]
+    fn test_clean_caption_and_tags() {
+        assert_eq!(clean_caption("OLYMPUS DIGITAL CAMERA  "), None);
+        assert_eq!(clean_caption(" Tram 28"), Some("Tram 28".to_string()));
+
+        let subject = ["Lisbon".to_string(), "tram".to_string()];
+        let keywords = ["lisbon".to_string(), " ".to_string(), "2023".to_string()];
+        assert_eq!(
+            keywords_to_tags(subject.iter().chain(&keywords)),
+            vec!["Lisbon", "tram", "2023"]
+        );
+    }
+}
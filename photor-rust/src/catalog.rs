@@ -0,0 +1,479 @@
+use crate::models::ColumnValue;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// A row of the catalog: its columns by name, and the nested rows (like the tags of a photo).
+pub type Record = Map<String, JsonValue>;
+
+/// The format of a catalog dump.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CatalogFormat {
+    /// One JSON object per line, in one file
+    Jsonl,
+    /// One CSV file per table, in a directory
+    Csv,
+}
+
+/// The content of the database a dump is made of. The photos are identified by their partial
+/// hash, which is how they're matched when a dump is imported.
+#[derive(Debug, Default, PartialEq)]
+pub struct Catalog {
+    /// The columns of the `photos` table, with their `tags`.
+    pub photos: Vec<Record>,
+    /// The columns of the `archives` table, with their `tags` and `holders`.
+    pub archives: Vec<Record>,
+    /// The photos of the archives: `archive_id`, `partial_sha256_hash` and `inserted_at`.
+    pub archive_items: Vec<Record>,
+}
+
+// The files of a CSV dump, one per table.
+const PHOTOS_CSV: &str = "photos.csv";
+const PHOTO_TAGS_CSV: &str = "photo_tags.csv";
+const ARCHIVES_CSV: &str = "archives.csv";
+const ARCHIVE_TAGS_CSV: &str = "archive_tags.csv";
+const ARCHIVE_HOLDERS_CSV: &str = "archive_holders.csv";
+const ARCHIVE_ITEMS_CSV: &str = "archive_items.csv";
+
+/// The kinds of records of a catalog dump.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    Photo,
+    Archive,
+    ArchiveItem,
+}
+
+impl RecordType {
+    // the `type` of the records of a JSON lines dump:
+    fn name(self) -> &'static str {
+        match self {
+            RecordType::Photo => "photo",
+            RecordType::Archive => "archive",
+            RecordType::ArchiveItem => "archive_item",
+        }
+    }
+}
+
+/// Writes a catalog dump record by record, so that it's never held in memory.
+pub struct CatalogWriter {
+    output: Output,
+    counts: HashMap<RecordType, usize>,
+}
+
+enum Output {
+    // JSON lines, each one having a `type`: `photo`, `archive` or `archive_item`.
+    Jsonl(Box<dyn Write>),
+    // CSV files in a directory, one per table, the nested rows (tags, holders) being in their own
+    // files. Their columns are the ones of their first row.
+    Csv {
+        directory: PathBuf,
+        files: HashMap<&'static str, (Vec<String>, csv::Writer<File>)>,
+    },
+}
+
+impl CatalogWriter {
+    pub fn jsonl(writer: Box<dyn Write>) -> Self {
+        CatalogWriter {
+            output: Output::Jsonl(writer),
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn csv(directory: &Path) -> Result<Self, String> {
+        fs::create_dir_all(directory).map_err(|err| err.to_string())?;
+        Ok(CatalogWriter {
+            output: Output::Csv {
+                directory: directory.to_path_buf(),
+                files: HashMap::new(),
+            },
+            counts: HashMap::new(),
+        })
+    }
+
+    pub fn write(&mut self, record_type: RecordType, record: Record) -> Result<(), String> {
+        *self.counts.entry(record_type).or_default() += 1;
+
+        match &mut self.output {
+            Output::Jsonl(writer) => {
+                let mut record = record;
+                record.insert("type".to_string(), record_type.name().into());
+                serde_json::to_writer(&mut *writer, &record).map_err(|err| err.to_string())?;
+                writeln!(writer).map_err(|err| err.to_string())
+            }
+            Output::Csv { directory, files } => {
+                let mut rows = vec![(
+                    match record_type {
+                        RecordType::Photo => PHOTOS_CSV,
+                        RecordType::Archive => ARCHIVES_CSV,
+                        RecordType::ArchiveItem => ARCHIVE_ITEMS_CSV,
+                    },
+                    record.clone(),
+                )];
+                match record_type {
+                    RecordType::Photo => rows.extend(
+                        unnest(&record, "partial_sha256_hash", "tags", "tag")
+                            .into_iter()
+                            .map(|row| (PHOTO_TAGS_CSV, row)),
+                    ),
+                    RecordType::Archive => {
+                        rows.extend(
+                            unnest(&record, "id", "tags", "tag")
+                                .into_iter()
+                                .map(|row| (ARCHIVE_TAGS_CSV, row)),
+                        );
+                        rows.extend(
+                            unnest(&record, "id", "holders", "")
+                                .into_iter()
+                                .map(|row| (ARCHIVE_HOLDERS_CSV, row)),
+                        );
+                    }
+                    RecordType::ArchiveItem => (),
+                }
+                for (name, row) in rows {
+                    write_csv_row(directory, files, name, &row)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// The number of records of the type written so far.
+    pub fn count(&self, record_type: RecordType) -> usize {
+        self.counts.get(&record_type).copied().unwrap_or(0)
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        match self.output {
+            Output::Jsonl(mut writer) => writer.flush().map_err(|err| err.to_string()),
+            Output::Csv {
+                directory,
+                mut files,
+            } => {
+                for name in [
+                    PHOTOS_CSV,
+                    PHOTO_TAGS_CSV,
+                    ARCHIVES_CSV,
+                    ARCHIVE_TAGS_CSV,
+                    ARCHIVE_HOLDERS_CSV,
+                    ARCHIVE_ITEMS_CSV,
+                ] {
+                    match files.remove(name) {
+                        Some((_, mut writer)) => writer.flush().map_err(|err| err.to_string())?,
+                        // the tables without rows have an empty file:
+                        None => {
+                            let path = directory.join(name);
+                            File::create(&path)
+                                .map_err(|err| format!("{}: {}", path.display(), err))?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn read_jsonl<R: BufRead>(reader: R) -> Result<Catalog, String> {
+    let mut catalog = Catalog::default();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut record: Record =
+            serde_json::from_str(&line).map_err(|err| format!("line {}: {}", index + 1, err))?;
+
+        match record.remove("type").as_ref().and_then(JsonValue::as_str) {
+            Some("photo") => catalog.photos.push(record),
+            Some("archive") => catalog.archives.push(record),
+            Some("archive_item") => catalog.archive_items.push(record),
+            other => {
+                return Err(format!(
+                    "line {}: unknown record type {:?}",
+                    index + 1,
+                    other.unwrap_or("")
+                ))
+            }
+        }
+    }
+    Ok(catalog)
+}
+
+pub fn read_csv(directory: &Path) -> Result<Catalog, String> {
+    let mut photos = read_csv_file(&directory.join(PHOTOS_CSV))?;
+    let mut archives = read_csv_file(&directory.join(ARCHIVES_CSV))?;
+
+    nest(
+        &mut photos,
+        read_csv_file(&directory.join(PHOTO_TAGS_CSV))?,
+        "partial_sha256_hash",
+        "tags",
+        "tag",
+    );
+    nest(
+        &mut archives,
+        read_csv_file(&directory.join(ARCHIVE_TAGS_CSV))?,
+        "id",
+        "tags",
+        "tag",
+    );
+    nest(
+        &mut archives,
+        read_csv_file(&directory.join(ARCHIVE_HOLDERS_CSV))?,
+        "id",
+        "holders",
+        "",
+    );
+
+    Ok(Catalog {
+        photos,
+        archives,
+        archive_items: read_csv_file(&directory.join(ARCHIVE_ITEMS_CSV))?,
+    })
+}
+
+/// Converts a value of the catalog to the type of the column it's imported into, as declared
+/// in the table (the CSV values being all strings).
+pub fn column_value(value: &JsonValue, declared_type: &str) -> ColumnValue {
+    let declared_type = declared_type.to_uppercase();
+    let text = match value {
+        JsonValue::String(text) => Some(text.clone()),
+        JsonValue::Null => None,
+        JsonValue::Bool(value) => Some((*value as i64).to_string()),
+        value => Some(value.to_string()),
+    };
+
+    // the affinity rules of SQLite:
+    if declared_type.contains("INT") || declared_type.contains("BOOL") {
+        match text.as_deref().map(str::parse::<i64>) {
+            None => ColumnValue::Integer(None),
+            Some(Ok(number)) => ColumnValue::Integer(Some(number)),
+            Some(Err(_)) => ColumnValue::Text(text),
+        }
+    } else if ["REAL", "FLOA", "DOUB"]
+        .iter()
+        .any(|name| declared_type.contains(name))
+    {
+        match text.as_deref().map(str::parse::<f64>) {
+            None => ColumnValue::Real(None),
+            Some(Ok(number)) => ColumnValue::Real(Some(number)),
+            Some(Err(_)) => ColumnValue::Text(text),
+        }
+    } else {
+        ColumnValue::Text(text)
+    }
+}
+
+// The rows nested in the record under `key`, with the `id` of the record added in the column
+// `parent`. The nested values which aren't objects (like the tags) are put in the column `name`.
+fn unnest(record: &Record, parent: &str, key: &str, name: &str) -> Vec<Record> {
+    let id = record.get(parent).cloned().unwrap_or(JsonValue::Null);
+    let nested = record.get(key).and_then(JsonValue::as_array);
+    nested
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            let mut row = match value {
+                JsonValue::Object(object) => object.clone(),
+                value => Record::from_iter([(name.to_string(), value.clone())]),
+            };
+            row.insert(parent_column(parent), id.clone());
+            row
+        })
+        .collect()
+}
+
+// The opposite of `unnest`.
+fn nest(records: &mut [Record], rows: Vec<Record>, parent: &str, key: &str, name: &str) {
+    let mut by_parent: HashMap<String, Vec<JsonValue>> = HashMap::new();
+    for mut row in rows {
+        let id = match row.remove(&parent_column(parent)) {
+            Some(JsonValue::String(id)) => id,
+            _ => continue,
+        };
+        let value = if name.is_empty() {
+            JsonValue::Object(row)
+        } else {
+            row.remove(name).unwrap_or(JsonValue::Null)
+        };
+        by_parent.entry(id).or_default().push(value);
+    }
+
+    for record in records.iter_mut() {
+        let nested = record
+            .get(parent)
+            .and_then(JsonValue::as_str)
+            .and_then(|id| by_parent.remove(id))
+            .unwrap_or_default();
+        record.insert(key.to_string(), JsonValue::Array(nested));
+    }
+}
+
+// The column of the nested rows referring to their record: `partial_sha256_hash` for the photos,
+// `archive_id` for the archives.
+fn parent_column(parent: &str) -> String {
+    match parent {
+        "id" => "archive_id".to_string(),
+        parent => parent.to_string(),
+    }
+}
+
+// Writes a row to a CSV file of the directory, created with the columns of the row (in the order
+// of the keys, alphabetical, without the nested rows) when it's the first one.
+fn write_csv_row(
+    directory: &Path,
+    files: &mut HashMap<&'static str, (Vec<String>, csv::Writer<File>)>,
+    name: &'static str,
+    row: &Record,
+) -> Result<(), String> {
+    let (columns, writer) = match files.entry(name) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let path = directory.join(name);
+            let file = File::create(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+            let mut writer = csv::Writer::from_writer(file);
+            let columns: Vec<String> = row
+                .iter()
+                .filter(|(_, value)| !value.is_array())
+                .map(|(column, _)| column.clone())
+                .collect();
+            writer
+                .write_record(&columns)
+                .map_err(|err| err.to_string())?;
+            entry.insert((columns, writer))
+        }
+    };
+
+    let values = columns.iter().map(|column| match row.get(column) {
+        None | Some(JsonValue::Null) => String::new(),
+        Some(JsonValue::String(text)) => text.clone(),
+        Some(value) => value.to_string(),
+    });
+    writer.write_record(values).map_err(|err| err.to_string())
+}
+
+// Reads the rows of a CSV file, the empty values being nulls. A missing file has no rows.
+fn read_csv_file(path: &Path) -> Result<Vec<Record>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let file = File::open(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let mut reader = csv::Reader::from_reader(BufReader::new(file));
+    let headers = reader
+        .headers()
+        .map_err(|err| format!("{}: {}", path.display(), err))?
+        .clone();
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|err| format!("{}: {}", path.display(), err))?;
+        let record = headers
+            .iter()
+            .zip(row.iter())
+            .map(|(column, value)| {
+                let value = if value.is_empty() {
+                    JsonValue::Null
+                } else {
+                    JsonValue::String(value.to_string())
+                };
+                (column.to_string(), value)
+            })
+            .collect();
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write(catalog: &Catalog, mut writer: CatalogWriter) {
+        let typed = [
+            (RecordType::Photo, &catalog.photos),
+            (RecordType::Archive, &catalog.archives),
+            (RecordType::ArchiveItem, &catalog.archive_items),
+        ];
+        for (record_type, records) in typed {
+            for record in records {
+                writer.write(record_type, record.clone()).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+    }
+
+    fn sample() -> Catalog {
+        let record = |value: JsonValue| value.as_object().unwrap().clone();
+        Catalog {
+            photos: vec![record(json!({
+                "id": "1",
+                "partial_sha256_hash": "abc",
+                "filename": "IMG_1.JPG",
+                "iso": "200",
+                "caption": null,
+                "tags": ["tram", "Lisbon"],
+            }))],
+            archives: vec![record(json!({
+                "id": "5f0e",
+                "media_type": "bluray",
+                "tags": [],
+                "holders": [{"name": "Alice", "given_from": "2023-01-01", "back_on": null}],
+            }))],
+            archive_items: vec![record(json!({
+                "archive_id": "5f0e",
+                "partial_sha256_hash": "abc",
+                "inserted_at": "2023-01-01 10:00:00",
+            }))],
+        }
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let path =
+            std::env::temp_dir().join(format!("photor-catalog-{}.jsonl", std::process::id()));
+        write(
+            &sample(),
+            CatalogWriter::jsonl(Box::new(File::create(&path).unwrap())),
+        );
+        let catalog = read_jsonl(BufReader::new(File::open(&path).unwrap()));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(catalog, Ok(sample()));
+        assert!(read_jsonl(&b"{\"type\": \"video\"}"[..]).is_err());
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let directory = std::env::temp_dir().join(format!("photor-catalog-{}", std::process::id()));
+        write(&sample(), CatalogWriter::csv(&directory).unwrap());
+        let catalog = read_csv(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(catalog, Ok(sample()));
+    }
+
+    #[test]
+    fn test_column_value() {
+        assert_eq!(
+            column_value(&json!("200"), "integer"),
+            ColumnValue::Integer(Some(200))
+        );
+        assert_eq!(
+            column_value(&json!(2.8), "REAL"),
+            ColumnValue::Real(Some(2.8))
+        );
+        assert_eq!(
+            column_value(&JsonValue::Null, "bigint"),
+            ColumnValue::Integer(None)
+        );
+        assert_eq!(
+            column_value(&json!(12), "text"),
+            ColumnValue::Text(Some("12".to_string()))
+        );
+    }
+}
@@ -5,18 +5,24 @@ use std::io::{self, BufReader, Read};
 /// Returns the sha256 hash of the file size and content of the first `nbytes`
 /// of the file.
 pub fn hash_file_first_bytes(file: &File, nbytes: u64) -> Result<String, String> {
-    let mut hasher = Sha256::new();
-
     let filesize = file
         .metadata()
         .map_err(|e| format!("Failed to get file metadata: {}", e))?
         .len();
 
-    hasher.update(filesize.to_string().as_bytes());
+    hash_first_bytes(BufReader::new(file), filesize, nbytes)
+}
+
+/// Same as `hash_file_first_bytes`, for any reader whose total size is known, like the members of
+/// zip or tar archives.
+pub fn hash_first_bytes<R: Read>(reader: R, size: u64, nbytes: u64) -> Result<String, String> {
+    let mut hasher = Sha256::new();
 
-    let mut file_reader = BufReader::new(file).take(nbytes);
+    hasher.update(size.to_string().as_bytes());
 
-    io::copy(&mut file_reader, &mut hasher)
+    let mut reader = reader.take(nbytes);
+
+    io::copy(&mut reader, &mut hasher)
         .map_err(|e| format!("Failed to compute sha256 hash: {}", e))?;
 
     Ok(format!("{:x}", hasher.finalize()))
@@ -50,6 +56,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_reader_same_as_file() {
+        let content = std::fs::read("tests/assets/checksum.txt").unwrap();
+        let hash = hash_first_bytes(&content[..], content.len() as u64, 1).unwrap();
+        assert_eq!(hash, compute_hash("tests/assets/checksum.txt", 1).unwrap());
+    }
+
     #[test]
     fn test_no_bytes() {
         let hash = compute_hash("tests/assets/checksum.txt", 0).unwrap();
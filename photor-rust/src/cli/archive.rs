@@ -9,9 +9,12 @@ pub struct ArchiveNewArgs {
 
 #[derive(Args)]
 pub struct ArchivePopulateArgs {
-    /// Argument for populate
-    #[arg(long)]
-    arg: i32,
+    /// The id of the archive
+    pub archive: String,
+
+    /// The query selecting the photos to add, like `date:2023 -archive:any`
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub query: Vec<String>,
 }
 
 #[derive(Subcommand)]
@@ -27,18 +30,3 @@ pub struct ArchiveArgs {
     #[command(subcommand)]
     pub command: ArchiveCommand,
 }
-
-pub fn match_subcommand(command: &ArchiveCommand) -> anyhow::Result<()> {
-    return match command {
-        ArchiveCommand::New(_args) => {
-            info!("to be implemented");
-            // Implementation here
-            Ok(())
-        }
-        ArchiveCommand::Populate(_args) => {
-            info!("to be implemented");
-            // Implementation here
-            Ok(())
-        }
-    };
-}
@@ -0,0 +1,44 @@
+use crate::catalog::CatalogFormat;
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct CatalogExportArgs {
+    /// The format of the dump
+    #[arg(long, value_enum, default_value_t = CatalogFormat::Jsonl)]
+    pub format: CatalogFormat,
+
+    /// Where to write the dump: a file for JSON lines (the standard output by default), a
+    /// directory for CSV
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Only exports the photos matching this query (with their archives)
+    #[arg(short, long, allow_hyphen_values = true)]
+    pub query: Option<String>,
+}
+
+#[derive(Args)]
+pub struct CatalogImportArgs {
+    /// The dump: a JSON lines file, or a directory of CSV files
+    pub path: PathBuf,
+
+    /// Replaces the photos and archives of the database with the ones of the dump, instead of
+    /// merging them
+    #[arg(long)]
+    pub rebuild: bool,
+}
+
+#[derive(Subcommand)]
+pub enum CatalogCommand {
+    /// Dumps the photos, archives, archive items and tags
+    Export(CatalogExportArgs),
+    /// Merges a dump into the database, matching the photos by their partial hash
+    Import(CatalogImportArgs),
+}
+
+#[derive(Args)]
+pub struct CatalogArgs {
+    #[command(subcommand)]
+    pub command: CatalogCommand,
+}
@@ -0,0 +1,36 @@
+use clap::{Args, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DbBackupArgs {
+    /// The directory where to write the backup, named after the current date and time
+    pub dest: PathBuf,
+
+    /// Removes the oldest backups of the directory, keeping this number of them
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub keep: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct DbRestoreArgs {
+    /// The backup to restore
+    pub backup: PathBuf,
+
+    /// Restores the backup even if it doesn't match the files of the repository
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Copies the database, even while it's in use, and checks the copy
+    Backup(DbBackupArgs),
+    /// Replaces the database with a backup (the current one being backed up first)
+    Restore(DbRestoreArgs),
+}
+
+#[derive(Args)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub command: DbCommand,
+}
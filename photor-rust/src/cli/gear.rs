@@ -0,0 +1,60 @@
+use crate::cli::output::OutputArgs;
+use crate::gear::GearKind;
+use clap::{Args, Subcommand};
+
+#[derive(Args)]
+pub struct GearListArgs {
+    /// Only lists the makes, models or lenses (all of them by default)
+    #[arg(long, value_enum)]
+    pub kind: Option<GearKind>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct GearAliasArgs {
+    #[arg(value_enum)]
+    pub kind: GearKind,
+
+    /// The name to replace, as shown by `photor gear list`
+    pub value: String,
+
+    /// The name to use instead
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct GearMergeArgs {
+    #[arg(value_enum)]
+    pub kind: GearKind,
+
+    /// The names to merge, as shown by `photor gear list`
+    #[arg(required = true)]
+    pub values: Vec<String>,
+
+    /// The name to give to all of them
+    #[arg(long)]
+    pub into: String,
+}
+
+#[derive(Subcommand)]
+pub enum GearCommand {
+    /// Lists the makes, models and lenses, with their number of photos and raw EXIF values
+    List(GearListArgs),
+    /// Renames a make, model or lens
+    Alias(GearAliasArgs),
+    /// Merges several makes, models or lenses under one name
+    Merge(GearMergeArgs),
+    /// Shows the usage of each camera body over time, with its estimated shutter count
+    Report,
+    /// Normalizes again the names of all the photos (like the ones imported before the
+    /// normalization existed)
+    Normalize,
+}
+
+#[derive(Args)]
+pub struct GearArgs {
+    #[command(subcommand)]
+    pub command: GearCommand,
+}
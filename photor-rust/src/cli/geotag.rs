@@ -0,0 +1,28 @@
+use crate::units;
+use chrono::Duration;
+use clap::Args;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct GeotagArgs {
+    /// The GPX file(s) recorded during the shooting (can be repeated)
+    #[arg(long, required = true)]
+    pub gpx: Vec<PathBuf>,
+
+    /// The maximum time between a photo and the nearest track point, like 5m or 90s
+    #[arg(long, value_parser = units::parse_duration, default_value = "5m")]
+    pub max_gap: Duration,
+
+    /// The offset of the camera clock from UTC (the camera time minus the UTC time), like +01:00
+    /// or -2h
+    #[arg(long, value_parser = units::parse_duration, default_value = "0s", allow_hyphen_values = true)]
+    pub offset: Duration,
+
+    /// Also replaces the locations previously set by a geotag
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Writes the proposed locations to the database. Without it, they are only shown
+    #[arg(long)]
+    pub apply: bool,
+}
@@ -1,8 +1,30 @@
+use crate::files::SymlinkPolicy;
 use clap::Args;
 use std::path::PathBuf;
 
 #[derive(Args)]
 pub struct ImportArgs {
-    /// Where the files to import are
-    pub directory: PathBuf,
+    /// Where the files to import are: directories, or zip and tar(.gz) archives (several can be
+    /// given)
+    #[arg(required_unless_present = "files_from")]
+    pub sources: Vec<PathBuf>,
+
+    /// Imports the files (or directories, archives) listed in this file, or in stdin with `-`. Paths are separated by
+    /// newlines, or by NUL characters (as output by `find -print0`)
+    #[arg(long, value_name = "PATH|-")]
+    pub files_from: Option<PathBuf>,
+
+    /// What to do with the symlinks found in the directory
+    #[arg(long, value_enum, default_value_t = SymlinkPolicy::Ignore)]
+    pub symlinks: SymlinkPolicy,
+
+    /// Excludes the files and directories matching this gitignore-style pattern (can be
+    /// repeated). `.photorignore` files found in the directory are also honored
+    #[arg(long = "exclude", value_name = "GLOB")]
+    pub excludes: Vec<String>,
+
+    /// Google Takeout (or phone export) mode: each media file is paired with its JSON sidecar,
+    /// whose date, location and description are used when the EXIF lacks them
+    #[arg(long)]
+    pub takeout: bool,
 }
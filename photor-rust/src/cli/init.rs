@@ -11,6 +11,6 @@ pub struct InitArgs {
 // pub fn run(opt_directory: &Option<PathBuf>) {
 pub fn run(args: &InitArgs) {
     let current_dir = std::env::current_dir().expect("Failed to get the current directory");
-    let dest = args.directory.as_ref().unwrap_or_else(|| &current_dir);
+    let dest = args.directory.as_ref().unwrap_or(&current_dir);
     println!("TODO: Initialization of a new repo in {:?}", dest);
 }
@@ -0,0 +1,161 @@
+use crate::cli::output::OutputArgs;
+use crate::database::PhotoSort;
+use crate::exposure::{self, Range};
+use crate::gps::{self, BoundingBox, Point};
+use crate::metadata::{self, MetaCondition};
+use crate::units;
+use chrono::{Duration, NaiveDate};
+use clap::Args;
+
+/// An import of the web app, by id or the last one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportSession {
+    Id(i64),
+    Last,
+}
+
+fn parse_import_session(value: &str) -> Result<ImportSession, String> {
+    match value {
+        "last" => Ok(ImportSession::Last),
+        id => id
+            .parse()
+            .map(ImportSession::Id)
+            .map_err(|_| format!("invalid import {}, expected an id or \"last\"", value)),
+    }
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// The query selecting the photos, like `make:ricoh iso>=3200 -tag:private` (see the README),
+    /// after the options
+    #[arg(value_name = "QUERY", allow_hyphen_values = true)]
+    pub query: Vec<String>,
+
+    /// Only lists the photos taken on this day or after, like 2023-05-01
+    #[arg(long, value_name = "DATE")]
+    pub from: Option<NaiveDate>,
+
+    /// Only lists the photos taken on this day or before
+    #[arg(long, value_name = "DATE")]
+    pub to: Option<NaiveDate>,
+
+    /// Only lists the photos taken with a camera of this make, as recorded or normalized (a part
+    /// of the name, case insensitive)
+    #[arg(long)]
+    pub make: Option<String>,
+
+    /// Only lists the photos taken with this camera model (a part of the name, case insensitive)
+    #[arg(long)]
+    pub model: Option<String>,
+
+    /// Only lists the photos taken with this lens (a part of the name, case insensitive)
+    #[arg(long)]
+    pub lens: Option<String>,
+
+    /// Only lists the files of this MIME type, like image/jpeg, or video/* for all the videos
+    #[arg(long, value_name = "TYPE")]
+    pub mime_type: Option<String>,
+
+    /// Only lists the photos taken with an ISO in the range, like 100..400 or 3200..
+    #[arg(long, value_name = "RANGE", value_parser = exposure::parse_range)]
+    pub iso: Option<Range>,
+
+    /// Only lists the files of a size in the range, like 1MB..10MB or ..500KB
+    #[arg(long, value_name = "RANGE", value_parser = units::parse_size_range)]
+    pub size: Option<Range>,
+
+    /// Only lists the photos of an import of the web app, given by id or `last`
+    #[arg(long, value_name = "ID", value_parser = parse_import_session)]
+    pub import: Option<ImportSession>,
+
+    /// Only lists the photos which aren't in any archive
+    #[arg(long, conflicts_with = "in_archive")]
+    pub not_archived: bool,
+
+    /// Only lists the photos of this archive
+    #[arg(long, value_name = "ID")]
+    pub in_archive: Option<String>,
+
+    /// Only lists the photos taken near this position, given as LAT,LON in decimal degrees
+    #[arg(long, value_name = "LAT,LON", value_parser = gps::parse_point, allow_hyphen_values = true)]
+    pub near: Option<Point>,
+
+    /// The distance from the --near position, like 2km or 500m
+    #[arg(long, value_parser = gps::parse_distance, default_value = "1km", requires = "near")]
+    pub radius: f64,
+
+    /// Only lists the photos taken within this box, given as MINLAT,MINLON,MAXLAT,MAXLON
+    #[arg(long, value_name = "MINLAT,MINLON,MAXLAT,MAXLON", value_parser = gps::parse_bounding_box, allow_hyphen_values = true)]
+    pub bbox: Option<BoundingBox>,
+
+    /// Only lists the photos taken in this city, region or country (a name or an ISO country
+    /// code, case insensitive)
+    #[arg(long)]
+    pub place: Option<String>,
+
+    /// Only lists the videos lasting at least this long, like 30s or 2m
+    #[arg(long, value_parser = units::parse_duration)]
+    pub min_duration: Option<Duration>,
+
+    /// Only lists the videos lasting at most this long, like 30s or 2m
+    #[arg(long, value_parser = units::parse_duration)]
+    pub max_duration: Option<Duration>,
+
+    /// Only lists the photos exposed longer than this time, like 1/30 or 2s
+    #[arg(long, value_name = "TIME", value_parser = exposure::parse_exposure_arg)]
+    pub shutter_slower_than: Option<f64>,
+
+    /// Only lists the photos exposed shorter than this time, like 1/1000
+    #[arg(long, value_name = "TIME", value_parser = exposure::parse_exposure_arg)]
+    pub shutter_faster_than: Option<f64>,
+
+    /// Only lists the photos taken with an f-number in the range, like 1.4..2.8 or ..4
+    #[arg(long, value_name = "RANGE", value_parser = exposure::parse_range)]
+    pub aperture: Option<Range>,
+
+    /// Only lists the photos taken with a focal length (in mm) in the range, like 20..35 or 50
+    #[arg(long, value_name = "RANGE", value_parser = exposure::parse_range)]
+    pub focal: Option<Range>,
+
+    /// Like --focal, with the 35mm equivalent focal length
+    #[arg(long, value_name = "RANGE", value_parser = exposure::parse_range)]
+    pub focal_35mm: Option<Range>,
+
+    /// Only lists the photos having this tag (from their keywords). Can be repeated
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Only lists the photos rated at least this number of stars
+    #[arg(long, value_name = "STARS", value_parser = clap::value_parser!(i64).range(-1..=5))]
+    pub min_rating: Option<i64>,
+
+    /// Only lists the photos whose raw metadata (the exiftool tags) matches the condition:
+    /// TAG=VALUE, TAG!=VALUE or TAG~VALUE (containing), like Flash=Fired. Can be repeated
+    #[arg(long = "where-meta", value_name = "CONDITION", value_parser = metadata::parse_condition)]
+    pub where_meta: Vec<MetaCondition>,
+
+    /// The order of the photos
+    #[arg(long, value_enum, default_value_t = PhotoSort::Date)]
+    pub sort: PhotoSort,
+
+    /// Lists the photos in the reverse order
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Lists this number of photos at most
+    #[arg(long)]
+    pub limit: Option<u64>,
+
+    /// Skips this number of photos first
+    #[arg(long)]
+    pub offset: Option<u64>,
+
+    /// Outputs the absolute paths of the files (relative to the repository by default)
+    #[arg(long)]
+    pub absolute: bool,
+
+    /// The columns are the ones of the photos, and the path of their file (the default format
+    /// being `paths`)
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use clap::{Args, Subcommand, ValueEnum};
+
+/// The data of the photos that can be refreshed from their files: the columns read from the
+/// EXIF, plus the location, the video metadata and the raw metadata.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+#[value(rename_all = "snake_case")]
+pub enum RefreshField {
+    ImageHeight,
+    ImageWidth,
+    MimeType,
+    Iso,
+    Aperture,
+    ShutterSpeed,
+    FocalLength,
+    ExposureSeconds,
+    FNumber,
+    FocalLengthMm,
+    #[value(name = "focal_length_35mm")]
+    FocalLength35mm,
+    Make,
+    Model,
+    LensInfo,
+    LensMake,
+    LensModel,
+    CameraSerial,
+    LensSerial,
+    ShutterCount,
+    /// The EXIF orientation, and the displayed dimensions
+    Orientation,
+    Rating,
+    ColorLabel,
+    /// The XMP, IPTC or EXIF caption (the captions are never removed)
+    Caption,
+    /// The XMP subject and IPTC keywords
+    Tags,
+    /// The EXIF GPS position and direction (the locations set by a geotag or a sidecar are kept)
+    Location,
+    /// The duration, codecs... of the videos
+    Video,
+    /// The complete exiftool output
+    Metadata,
+}
+
+#[derive(Args)]
+pub struct RefreshArgs {
+    /// Only refreshes these fields (all of them by default). Can be repeated
+    #[arg(long = "field", value_enum)]
+    pub fields: Vec<RefreshField>,
+
+    /// Only refreshes the photos taken since this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+
+    /// Only refreshes the photos missing one of the fields
+    #[arg(long)]
+    pub only_missing: bool,
+
+    /// Starts over, instead of resuming an interrupted refresh with the same options
+    #[arg(long)]
+    pub restart: bool,
+
+    /// The number of photos updated per transaction
+    #[arg(long, default_value_t = 100)]
+    pub batch_size: u32,
+}
+
+#[derive(Subcommand)]
+pub enum MetadataCommand {
+    /// Re-reads the metadata of the repository files, to complete or update the database
+    Refresh(RefreshArgs),
+}
+
+#[derive(Args)]
+pub struct MetadataArgs {
+    #[command(subcommand)]
+    pub command: MetadataCommand,
+}
@@ -0,0 +1,36 @@
+use crate::cli::output::OutputArgs;
+use clap::{Args, Subcommand};
+
+#[derive(Args)]
+pub struct MigrateDownArgs {
+    /// Reverts all the migrations after this version (only the last one by default), 0 reverting
+    /// all of them
+    #[arg(long, value_name = "VERSION")]
+    pub to: Option<i64>,
+}
+
+#[derive(Args)]
+pub struct MigrateStatusArgs {
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Subcommand)]
+pub enum MigrateCommand {
+    /// Applies the pending migrations (the default)
+    Up,
+    /// Lists the migrations, applied and pending, with their checksums
+    Status(MigrateStatusArgs),
+    /// Reverts migrations
+    Down(MigrateDownArgs),
+}
+
+#[derive(Args)]
+pub struct MigrateArgs {
+    #[command(subcommand)]
+    pub command: Option<MigrateCommand>,
+
+    /// Doesn't back up the database before changing it
+    #[arg(long, global = true)]
+    pub no_backup: bool,
+}
@@ -1,14 +1,35 @@
+use crate::commands::archive as cmd_archive;
+use crate::commands::catalog as cmd_catalog;
+use crate::commands::db as cmd_db;
+use crate::commands::gear as cmd_gear;
+use crate::commands::geotag as cmd_geotag;
 use crate::commands::import as cmd_import;
 use crate::commands::list_photos as cmd_list_photos;
-use crate::database;
+use crate::commands::metadata as cmd_metadata;
+use crate::commands::migrate as cmd_migrate;
+use crate::commands::places as cmd_places;
+use crate::commands::query as cmd_query;
+use crate::commands::show as cmd_show;
+use crate::commands::stats as cmd_stats;
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
 // use crate::cli::{Cli, Commands};
 
 pub mod archive;
+pub mod catalog;
+pub mod db;
+pub mod gear;
+pub mod geotag;
 pub mod import;
 pub mod init;
+pub mod list;
+pub mod metadata;
+pub mod migrate;
+pub mod output;
+pub mod places;
+pub mod query;
+pub mod show;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -31,15 +52,42 @@ pub enum Commands {
     Init(init::InitArgs),
 
     /// List photos
-    List,
+    List(Box<list::ListArgs>),
+
+    /// Shows all about a photo, or whether a file is already in the repository
+    Show(show::ShowArgs),
 
     /// Import photos from a directory
     Import(import::ImportArgs),
 
-    /// Migrate the database
-    Migrate,
+    /// Migrates the database, or shows or reverts its migrations
+    Migrate(migrate::MigrateArgs),
+
+    /// Backs up and restores the database
+    Db(db::DbArgs),
 
     Archive(archive::ArchiveArgs),
+
+    /// Saves, lists and deletes the queries selecting photos
+    Query(query::QueryArgs),
+
+    /// Exports the catalog to JSON lines or CSV files, or imports such a dump
+    Catalog(catalog::CatalogArgs),
+
+    /// Sets the location of the photos from GPX tracks, matching their dates
+    Geotag(geotag::GeotagArgs),
+
+    /// Counts the photos by place (country, region, city)
+    Places(places::PlacesArgs),
+
+    /// Normalizes and renames the cameras and lenses
+    Gear(gear::GearArgs),
+
+    /// Maintains the metadata of the photos already imported
+    Metadata(metadata::MetadataArgs),
+
+    /// Shows the number and size of the photos and videos
+    Stats,
 }
 
 pub async fn run() -> anyhow::Result<()> {
@@ -49,14 +97,33 @@ pub async fn run() -> anyhow::Result<()> {
     // matches just as you would the top level cmd
     match &cli.command {
         Some(Commands::Init(args)) => init::run(args),
-        Some(Commands::Migrate) => return database::migrate().await,
-        Some(Commands::List) => return cmd_list_photos::run().await,
+        Some(Commands::Migrate(migrate_args)) => return cmd_migrate::run(migrate_args).await,
+        Some(Commands::Db(db_args)) => return cmd_db::run(db_args).await,
+        Some(Commands::List(list_args)) => return cmd_list_photos::run(list_args).await,
+        Some(Commands::Show(show_args)) => return cmd_show::run(show_args).await,
         Some(Commands::Import(import_args)) => {
-            return cmd_import::run(&import_args.directory).await;
+            let options = cmd_import::ImportOptions {
+                symlinks: import_args.symlinks,
+                takeout: import_args.takeout,
+            };
+            return cmd_import::run(
+                &import_args.sources,
+                import_args.files_from.as_deref(),
+                &import_args.excludes,
+                options,
+            )
+            .await;
         }
-        Some(Commands::Archive(archive_args)) => {
-            return archive::match_subcommand(&archive_args.command)
+        Some(Commands::Catalog(catalog_args)) => return cmd_catalog::run(catalog_args).await,
+        Some(Commands::Geotag(geotag_args)) => return cmd_geotag::run(geotag_args).await,
+        Some(Commands::Places(places_args)) => return cmd_places::run(places_args).await,
+        Some(Commands::Gear(gear_args)) => return cmd_gear::run(gear_args).await,
+        Some(Commands::Metadata(metadata_args)) => {
+            return cmd_metadata::run(&metadata_args.command).await
         }
+        Some(Commands::Stats) => return cmd_stats::run().await,
+        Some(Commands::Archive(archive_args)) => return cmd_archive::run(archive_args).await,
+        Some(Commands::Query(query_args)) => return cmd_query::run(query_args).await,
 
         None => (),
     };
@@ -0,0 +1,14 @@
+use crate::output::OutputFormat;
+use clap::Args;
+
+/// The options of the listing commands.
+#[derive(Args)]
+pub struct OutputArgs {
+    /// The output format
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// The columns to output, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+}
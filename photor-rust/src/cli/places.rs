@@ -0,0 +1,24 @@
+use crate::cli::output::OutputArgs;
+use clap::{Args, ValueEnum};
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum PlaceLevel {
+    Country,
+    Region,
+    City,
+}
+
+#[derive(Args)]
+pub struct PlacesArgs {
+    /// How finely the photos are grouped
+    #[arg(long, value_enum, default_value_t = PlaceLevel::City)]
+    pub by: PlaceLevel,
+
+    /// First finds the places of the photos having a location but no place yet (like the ones
+    /// imported before places were supported)
+    #[arg(long)]
+    pub refresh: bool,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
@@ -0,0 +1,39 @@
+use crate::cli::output::OutputArgs;
+use clap::{Args, Subcommand};
+
+#[derive(Args)]
+pub struct QuerySaveArgs {
+    /// The name of the query, to be used as @NAME in the other queries
+    pub name: String,
+
+    /// The query, like make:ricoh iso>=3200 -tag:private
+    #[arg(required = true, allow_hyphen_values = true)]
+    pub query: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct QueryListArgs {
+    #[command(flatten)]
+    pub output: OutputArgs,
+}
+
+#[derive(Args)]
+pub struct QueryDeleteArgs {
+    pub name: String,
+}
+
+#[derive(Subcommand)]
+pub enum QueryCommand {
+    /// Saves a query by name, replacing the one of the same name
+    Save(QuerySaveArgs),
+    /// Lists the saved queries
+    List(QueryListArgs),
+    /// Deletes a saved query
+    Delete(QueryDeleteArgs),
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    #[command(subcommand)]
+    pub command: QueryCommand,
+}
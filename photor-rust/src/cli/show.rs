@@ -0,0 +1,8 @@
+use clap::Args;
+
+#[derive(Args)]
+pub struct ShowArgs {
+    /// The id of the photo, its partial hash or the path of its file. A file out of the
+    /// repository (like one to import) is looked up by its partial hash.
+    pub photo: String,
+}
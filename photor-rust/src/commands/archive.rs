@@ -0,0 +1,26 @@
+use crate::cli::archive::{ArchiveArgs, ArchiveCommand, ArchivePopulateArgs};
+use crate::commands::query;
+use crate::database::{Store, StoreError};
+
+pub async fn run(args: &ArchiveArgs) -> anyhow::Result<()> {
+    match &args.command {
+        ArchiveCommand::New(_args) => {
+            info!("to be implemented");
+            // Implementation here
+            Ok(())
+        }
+        ArchiveCommand::Populate(populate_args) => populate(populate_args).await,
+    }
+}
+
+async fn populate(args: &ArchivePopulateArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+    let selection = query::load(&store, &args.query).await?;
+
+    let added = match store.add_to_archive(&args.archive, &selection).await {
+        Err(StoreError::NotFound) => anyhow::bail!("No archive {}", args.archive),
+        result => result?,
+    };
+    info!("{} photo(s) added to the archive {}", added, args.archive);
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use crate::catalog::{self, CatalogFormat, CatalogWriter, RecordType};
+use crate::cli::catalog::{CatalogArgs, CatalogCommand, CatalogExportArgs, CatalogImportArgs};
+use crate::commands::db::{self as cmd_db, BACKUP_DIRECTORY};
+use crate::commands::query;
+use crate::database::{self as db, Store};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+pub async fn run(args: &CatalogArgs) -> anyhow::Result<()> {
+    match &args.command {
+        CatalogCommand::Export(export_args) => export(export_args).await,
+        CatalogCommand::Import(import_args) => import(import_args).await,
+    }
+}
+
+async fn export(args: &CatalogExportArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+    let selection = query::load(&store, args.query.as_slice()).await?;
+    let mut writer = match (args.format, &args.output) {
+        (CatalogFormat::Jsonl, None) => {
+            CatalogWriter::jsonl(Box::new(BufWriter::new(io::stdout().lock())))
+        }
+        (CatalogFormat::Jsonl, Some(path)) => {
+            CatalogWriter::jsonl(Box::new(BufWriter::new(File::create(path)?)))
+        }
+        (CatalogFormat::Csv, Some(directory)) => CatalogWriter::csv(directory)
+            .map_err(|err| anyhow::anyhow!("Failed to write the catalog: {}", err))?,
+        (CatalogFormat::Csv, None) => {
+            anyhow::bail!("The CSV files need a directory, given with --output")
+        }
+    };
+
+    store
+        .for_each_catalog_record(&selection, |record_type, record| {
+            writer
+                .write(record_type, record)
+                .map_err(|err| anyhow::anyhow!("Failed to write the catalog: {}", err))
+        })
+        .await?;
+    let (photos, archives, archive_items) = (
+        writer.count(RecordType::Photo),
+        writer.count(RecordType::Archive),
+        writer.count(RecordType::ArchiveItem),
+    );
+    writer
+        .finish()
+        .map_err(|err| anyhow::anyhow!("Failed to write the catalog: {}", err))?;
+
+    info!(
+        "{} photo(s), {} archive(s) and {} archive item(s) exported",
+        photos, archives, archive_items
+    );
+    Ok(())
+}
+
+async fn import(args: &CatalogImportArgs) -> anyhow::Result<()> {
+    // the whole dump is read before the database is changed:
+    let catalog = if args.path.is_dir() {
+        catalog::read_csv(&args.path)
+    } else {
+        catalog::read_jsonl(BufReader::new(File::open(&args.path)?))
+    }
+    .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", args.path.display(), err))?;
+
+    let store = Store::open().await?;
+    if args.rebuild {
+        let saved = cmd_db::backup_to_directory(
+            &store,
+            Path::new(BACKUP_DIRECTORY),
+            Some("before-catalog-import"),
+        )
+        .await?;
+        info!("Current database backed up to {}", saved.display());
+    }
+
+    let mut tx = store.begin().await?;
+    let counts = db::import_catalog(&mut tx, &catalog, args.rebuild).await?;
+    tx.commit().await?;
+
+    info!(
+        "{} photo(s), {} photo tag(s), {} archive(s), {} archive tag(s) and {} archive item(s) added",
+        counts.photos,
+        counts.photo_tags,
+        counts.archives,
+        counts.archive_tags,
+        counts.archive_items
+    );
+    if counts.skipped_items > 0 {
+        warn!(
+            "{} archive item(s) skipped, their photo being neither in the dump nor in the database",
+            counts.skipped_items
+        );
+    }
+    if args.rebuild {
+        info!("Run `photor metadata refresh` to rebuild the metadata and videos of the photos");
+    }
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use crate::cli::db::{DbArgs, DbBackupArgs, DbCommand, DbRestoreArgs};
+use crate::database::{Store, StoreError};
+use crate::schema::MigrationState;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// where the database is backed up before it's changed by a migration or a restore:
+pub const BACKUP_DIRECTORY: &str = "backups";
+
+const DATABASE_FILE: &str = "db.sqlite";
+
+pub async fn run(args: &DbArgs) -> anyhow::Result<()> {
+    match &args.command {
+        DbCommand::Backup(backup_args) => backup(backup_args).await,
+        DbCommand::Restore(restore_args) => restore(restore_args).await,
+    }
+}
+
+async fn backup(args: &DbBackupArgs) -> anyhow::Result<()> {
+    let store = Store::connect().await?;
+    let path = backup_to_directory(&store, &args.dest, None).await?;
+    info!("Database backed up to {}", path.display());
+
+    if let Some(keep) = args.keep {
+        let names: Vec<String> = fs::read_dir(&args.dest)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        for name in backups_to_remove(&names, keep as usize) {
+            let old = args.dest.join(&name);
+            fs::remove_file(&old)?;
+            info!("Old backup {} removed", old.display());
+        }
+    }
+
+    Ok(())
+}
+
+async fn restore(args: &DbRestoreArgs) -> anyhow::Result<()> {
+    // the connection is lazy, the file is read by the integrity check:
+    let backup = Store::open_copy(&args.backup).await?;
+    let problems = backup.integrity_check().await.map_err(|err| {
+        anyhow::anyhow!(
+            "Failed to read the backup {}: {}",
+            args.backup.display(),
+            err
+        )
+    })?;
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "The backup {} is corrupt: {}",
+            args.backup.display(),
+            problems.join(", ")
+        );
+    }
+
+    let migrations = backup.migrations().await?;
+    if let Some(migration) = migrations
+        .iter()
+        .find(|migration| migration.state == MigrationState::Unknown)
+    {
+        anyhow::bail!(
+            "The backup was migrated by a newer version of photor (migration {})",
+            migration.version
+        );
+    }
+
+    // the backup should index the files of the repository, and all of them:
+    let mut backup_hashes: HashSet<String> = HashSet::new();
+    let (mut indexed, mut missing) = (0, 0);
+    backup
+        .for_each_photo_file(|file| {
+            indexed += 1;
+            if !Path::new(&file.directory).join(&file.filename).exists() {
+                missing += 1;
+            }
+            backup_hashes.insert(file.partial_sha256_hash);
+        })
+        .await?;
+
+    let current = Store::connect().await?;
+    let mut dropped = 0;
+    let counted = current
+        .for_each_photo_file(|file| {
+            if !backup_hashes.contains(&file.partial_sha256_hash) {
+                dropped += 1;
+            }
+        })
+        .await;
+    if let Err(err) = counted {
+        warn!("Failed to read the photos of the current database: {}", err);
+        dropped = 0;
+    }
+
+    info!(
+        "The backup indexes {} photo(s), {} of them missing from the repository, and misses {} photo(s) of the current database",
+        indexed,
+        missing,
+        dropped
+    );
+    if (missing > 0 || dropped > 0) && !args.force {
+        anyhow::bail!(
+            "The backup doesn't match the files of the repository, use --force to restore it anyway"
+        );
+    }
+
+    current.close().await;
+
+    // the database file is replaced, so no other process must have it open (its WAL would be
+    // lost), and nothing must be written to it until then:
+    let current = match Store::connect_exclusive().await {
+        Ok(store) => store,
+        Err(StoreError::Busy) => {
+            anyhow::bail!("The database is in use (by the web app?), stop it before restoring")
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let saved = backup_to_directory(
+        &current,
+        Path::new(BACKUP_DIRECTORY),
+        Some("before-restore"),
+    )
+    .await?;
+    info!("Current database backed up to {}", saved.display());
+
+    let restoring = PathBuf::from(format!("{}.restoring", DATABASE_FILE));
+    let _ = fs::remove_file(&restoring);
+    backup.backup(&restoring).await?;
+    backup.close().await;
+
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", DATABASE_FILE, suffix));
+    }
+    fs::rename(&restoring, DATABASE_FILE)?;
+    // the lock is released once the restored database is in place:
+    current.close().await;
+    info!("Database restored from {}", args.backup.display());
+
+    if migrations
+        .iter()
+        .any(|migration| migration.state == MigrationState::Pending)
+    {
+        warn!("The backup was made by an older version of photor, run `photor migrate`");
+    }
+
+    Ok(())
+}
+
+/// Copies the database in the directory, to a file named after the current date and time (and
+/// the suffix), and checks the integrity of the copy. Returns its path.
+pub async fn backup_to_directory(
+    store: &Store,
+    directory: &Path,
+    suffix: Option<&str>,
+) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+
+    let mut base = format!("db-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    if let Some(suffix) = suffix {
+        base = format!("{}-{}", base, suffix);
+    }
+    // a number is added to the names of the backups made in the same second:
+    let mut name = base.clone();
+    let mut count = 1;
+    while directory.join(format!("{}.sqlite", name)).exists() {
+        count += 1;
+        name = format!("{}-{}", base, count);
+    }
+    let path = directory.join(format!("{}.sqlite", name));
+
+    // the copy gets its final name once checked, a failed backup can't be mistaken for a good one:
+    let partial = directory.join(format!("{}.sqlite.partial", name));
+    let _ = fs::remove_file(&partial);
+    store
+        .backup(&partial)
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to back up the database: {}", err))?;
+
+    let copy = Store::open_copy(&partial).await?;
+    let problems = copy.integrity_check().await?;
+    copy.close().await;
+    if !problems.is_empty() {
+        let _ = fs::remove_file(&partial);
+        anyhow::bail!("The backup is corrupt: {}", problems.join(", "));
+    }
+
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+// The oldest backups made by `photor db backup` among the file names, beyond the `keep` most
+// recent ones. The other files (like the backups made before the migrations) are left alone.
+fn backups_to_remove(names: &[String], keep: usize) -> Vec<String> {
+    lazy_static! {
+        static ref BACKUP_RE: Regex = Regex::new(r"^db-\d{8}-\d{6}(-\d+)?\.sqlite$").unwrap();
+    }
+
+    let mut backups: Vec<&String> = names
+        .iter()
+        .filter(|name| BACKUP_RE.is_match(name))
+        .collect();
+    // the names sort by date, once without extension (`db-…-2` coming after `db-…`):
+    backups.sort_unstable_by_key(|name| std::cmp::Reverse(name.trim_end_matches(".sqlite")));
+    backups.into_iter().skip(keep).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_to_remove() {
+        let names: Vec<String> = [
+            "db-20260101-100000.sqlite",
+            "db-20260301-100000.sqlite",
+            "db-20260201-100000-before-migrate.sqlite",
+            "db-20260201-100000.sqlite",
+            "db-20260201-100000-2.sqlite",
+            "notes.txt",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+        assert_eq!(
+            backups_to_remove(&names, 2),
+            vec!["db-20260201-100000.sqlite", "db-20260101-100000.sqlite"]
+        );
+        assert!(backups_to_remove(&names, 4).is_empty());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::cli::gear::{GearArgs, GearCommand, GearListArgs};
+use crate::database::{self, Store};
+use crate::gear::GearKind;
+use crate::output::{self, Listing, OutputFormat};
+use serde_json::Value as JsonValue;
+
+pub async fn run(args: &GearArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+
+    let aliases: Vec<(GearKind, &String, &String)> = match &args.command {
+        GearCommand::List(list_args) => {
+            let kinds = match list_args.kind {
+                Some(kind) => vec![kind],
+                None => vec![GearKind::Make, GearKind::Model, GearKind::Lens],
+            };
+            return list(&store, &kinds, list_args).await;
+        }
+        GearCommand::Alias(alias_args) => {
+            vec![(alias_args.kind, &alias_args.value, &alias_args.name)]
+        }
+        GearCommand::Merge(merge_args) => merge_args
+            .values
+            .iter()
+            .map(|value| (merge_args.kind, value, &merge_args.into))
+            .collect(),
+        GearCommand::Report => return report(&store).await,
+        GearCommand::Normalize => Vec::new(),
+    };
+
+    // the aliases are applied to the photos right away:
+    let mut tx = store.begin().await?;
+    for (kind, value, name) in aliases {
+        database::set_gear_alias(&mut tx, kind, value, name).await?;
+    }
+    let changed = database::normalize_all_gear(&mut tx).await?;
+    tx.commit().await?;
+    info!("Gear names updated for {} photo(s)", changed);
+
+    Ok(())
+}
+
+async fn list(store: &Store, kinds: &[GearKind], args: &GearListArgs) -> anyhow::Result<()> {
+    let mut listing = Listing::new(&["kind", "photos", "name", "raw_values"]);
+    for kind in kinds {
+        for count in store.count_photos_by_gear(*kind).await? {
+            let raw_values: Vec<JsonValue> = count
+                .raw_values
+                .unwrap_or_default()
+                .lines()
+                .map(JsonValue::from)
+                .collect();
+            listing.rows.push(vec![
+                kind.as_str().into(),
+                count.count.into(),
+                count.name.into(),
+                raw_values.into(),
+            ]);
+        }
+    }
+    output::print(
+        listing,
+        args.output.format.unwrap_or(OutputFormat::Table),
+        &args.output.columns,
+    )?;
+
+    Ok(())
+}
+
+async fn report(store: &Store) -> anyhow::Result<()> {
+    let bodies = store.body_usages().await?;
+    let years = store.body_usages_by_year().await?;
+
+    for (i, body) in bodies.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let name = [body.make.as_deref(), body.model.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ");
+        match &body.serial {
+            Some(serial) => println!("{} (serial number {})", name, serial),
+            None => println!("{} (unknown serial number)", name),
+        }
+
+        println!("  photos:        {}", body.photos);
+        println!("  first shot:    {}", body.first_shot);
+        println!("  last shot:     {}", body.last_shot);
+        match (body.shutter_count, &body.shutter_count_date) {
+            (Some(count), Some(date)) => {
+                println!("  shutter count: {} on {}", count, date);
+                if let Some(estimated) = body.estimated_shutter_count {
+                    println!("  estimated now: {}", estimated);
+                }
+            }
+            // without shutter count in the metadata, only the photos in the repository are known:
+            _ => println!("  shutter count: unknown, at least {}", body.photos),
+        }
+
+        let body_years: Vec<String> = years
+            .iter()
+            .filter(|year| {
+                (&year.make, &year.model, &year.serial) == (&body.make, &body.model, &body.serial)
+            })
+            .map(|year| format!("{}: {}", year.year, year.photos))
+            .collect();
+        println!("  per year:      {}", body_years.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::cli::geotag::GeotagArgs;
+use crate::database::{self, Store};
+use crate::gpx::{self, Track};
+use crate::places;
+
+pub async fn run(args: &GeotagArgs) -> anyhow::Result<()> {
+    let mut points = Vec::new();
+    for path in &args.gpx {
+        let mut file_points = gpx::read(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {}", path.display(), err))?;
+        info!(
+            "{} timed points read from {}",
+            file_points.len(),
+            path.display()
+        );
+        points.append(&mut file_points);
+    }
+
+    let track = Track::new(points);
+    let (start, end) = match track.time_range() {
+        Some(range) => range,
+        None => anyhow::bail!("No timed points found in the GPX file(s)"),
+    };
+
+    // the photos dates are in the camera time:
+    let camera_time = |time: chrono::DateTime<chrono::Utc>| (time + args.offset).naive_utc();
+    let store = Store::open().await?;
+    let photos = store
+        .photos_to_geotag(
+            camera_time(start - args.max_gap),
+            camera_time(end + args.max_gap),
+            args.overwrite,
+        )
+        .await?;
+
+    println!(
+        "{:>8}  {:<40}  {:<19}  {:>10}  {:>11}  {:>8}",
+        "ID", "FILE", "DATE", "LATITUDE", "LONGITUDE", "GAP"
+    );
+
+    let mut matches = Vec::new();
+    for photo in photos {
+        let time = photo.create_date.and_utc() - args.offset;
+        let path = format!("{}/{}", photo.directory, photo.filename);
+
+        match track.locate(time, args.max_gap) {
+            Some(location) => {
+                println!(
+                    "{:>8}  {:<40}  {:<19}  {:>10.6}  {:>11.6}  {:>7}s",
+                    photo.id,
+                    path,
+                    photo.create_date.to_string(),
+                    location.latitude,
+                    location.longitude,
+                    location.gap.num_seconds()
+                );
+                matches.push((photo.id, location));
+            }
+            None => println!(
+                "{:>8}  {:<40}  {:<19}  {:>10}  {:>11}  {:>8}",
+                photo.id,
+                path,
+                photo.create_date.to_string(),
+                "-",
+                "-",
+                "-"
+            ),
+        }
+    }
+
+    if !args.apply {
+        println!(
+            "{} photo(s) can be geotagged. Run again with --apply to write the locations.",
+            matches.len()
+        );
+        return Ok(());
+    }
+
+    let mut tx = store.begin().await?;
+    for (id, location) in &matches {
+        database::set_photo_location(
+            &mut tx,
+            *id,
+            location.latitude,
+            location.longitude,
+            location.elevation,
+            "gpx",
+        )
+        .await?;
+        let place = places::locate(location.latitude, location.longitude);
+        database::set_photo_place(&mut tx, *id, &place).await?;
+    }
+    tx.commit().await?;
+
+    println!("{} photo(s) geotagged.", matches.len());
+
+    Ok(())
+}
@@ -1,89 +1,607 @@
 use crate::checksum;
-use crate::database;
-use crate::files;
+use crate::database::{Store, StoreError, StoreResult};
+use crate::exclusions::Exclusions;
+use crate::files::{self, ArchiveFormat, SymlinkPolicy};
+use crate::metadata;
 use crate::models::NewPhoto;
 use crate::photoexif;
+use crate::places::{self, Place};
+use crate::takeout::{self, Sidecar};
+use chrono::NaiveDateTime;
+use flate2::read::GzDecoder;
 use log::{error, info};
-use sqlx::sqlite::SqlitePool;
-use std::fs::File;
-use std::path::Path;
+use std::fmt;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use tar::Archive as TarArchive;
+use zip::ZipArchive;
 
-const PARTIAL_HASH_NBYTES: u64 = 1024 * 512;
+// the files are identified by the hash of their size and first bytes:
+pub const PARTIAL_HASH_NBYTES: u64 = 1024 * 512;
 
-pub async fn run(directory: &Path) -> anyhow::Result<()> {
-    let pool = database::pool().await?;
-    for file in files::find_photo_files(directory) {
-        let photo_path = file.path();
+// how many times an operation is retried while the database is locked, and the first delay
+// between two attempts (doubled at each attempt):
+const BUSY_RETRIES: u32 = 3;
+const BUSY_FIRST_DELAY: Duration = Duration::from_millis(100);
 
-        let mut file = match File::open(photo_path).map_err(|_| "Failed to open the file") {
-            Ok(file) => file,
+/// Why a photo could not be imported.
+#[derive(Debug)]
+enum ImportError {
+    /// Reading, hashing or placing the file failed.
+    Failed(String),
+    Store(StoreError),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Failed(message) => write!(f, "{}", message),
+            ImportError::Store(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<String> for ImportError {
+    fn from(message: String) -> Self {
+        ImportError::Failed(message)
+    }
+}
 
-            Err(err) => {
-                error!("Failed to open the file {}: {}", photo_path.display(), err);
-                continue;
+impl From<StoreError> for ImportError {
+    fn from(err: StoreError) -> Self {
+        ImportError::Store(err)
+    }
+}
+
+/// How the sources are walked and their files imported.
+#[derive(Clone, Copy, Debug)]
+pub struct ImportOptions {
+    pub symlinks: SymlinkPolicy,
+    /// Google Takeout mode: the JSON sidecars of the media files are read.
+    pub takeout: bool,
+}
+
+/// Imports the given directories and archives, then the paths listed in `files_from` (a file,
+/// or `-` for stdin). The `excludes` globs apply to all of them.
+pub async fn run(
+    sources: &[PathBuf],
+    files_from: Option<&Path>,
+    excludes: &[String],
+    options: ImportOptions,
+) -> anyhow::Result<()> {
+    let mut store = Store::open().await?;
+    if let Some(import_id) = store.start_import().await? {
+        info!("Import #{} of the web app started", import_id);
+    }
+    let mut exclusions = Exclusions::new(excludes).map_err(anyhow::Error::msg)?;
+
+    for source in sources {
+        import_source(&store, source, options, &mut exclusions).await?;
+    }
+
+    if let Some(list_path) = files_from {
+        let paths = files::read_paths_list(list_path).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to read the list of files {}: {}",
+                list_path.display(),
+                err
+            )
+        })?;
+
+        for path in paths {
+            if path.is_dir() || ArchiveFormat::from_path(&path).is_some() {
+                // directories and archives in the list are imported like the ones given as
+                // arguments:
+                import_source(&store, &path, options, &mut exclusions).await?;
+            } else if !files::has_photo_extension(&path) {
+                warn!("{} is not a photo file, skipping...", path.display());
+            } else if !exclusions.is_excluded_listed(&path) {
+                import_file(&store, &path, options.takeout).await?;
             }
-        };
+        }
+    }
 
-        let partial_hash = match checksum::hash_file_first_bytes(&mut file, PARTIAL_HASH_NBYTES) {
-            Ok(partial_hash) => partial_hash,
+    exclusions.report();
 
-            Err(err) => {
-                error!(
-                    "Failed to calculate the partial hash of the file {}: {}",
-                    photo_path.display(),
-                    err
-                );
-                continue;
+    Ok(())
+}
+
+/// Imports a directory, or a zip or tar archive. Only the errors making the whole import
+/// pointless (like an unexpected database schema) are returned, the other ones are logged.
+async fn import_source(
+    store: &Store,
+    source: &Path,
+    options: ImportOptions,
+    exclusions: &mut Exclusions,
+) -> StoreResult<()> {
+    match ArchiveFormat::from_path(source) {
+        Some(format) if source.is_file() => {
+            match import_archive(store, source, format, options.takeout, exclusions).await {
+                Ok(result) => result,
+                Err(err) => {
+                    error!("Failed to import the archive {}: {}", source.display(), err);
+                    Ok(())
+                }
             }
-        };
-
-        match database::photo_lookup_by_partial_hash(&pool, &partial_hash).await {
-            Some(photo_in_db) => {
-                info!(
-                    "{}  already in DB (in {}/{}), skipping...",
-                    photo_path.display(),
-                    photo_in_db.directory,
-                    photo_in_db.filename
-                );
-                continue;
+        }
+        _ => import_directory(store, source, options, exclusions).await,
+    }
+}
+
+async fn import_directory(
+    store: &Store,
+    directory: &Path,
+    options: ImportOptions,
+    exclusions: &mut Exclusions,
+) -> StoreResult<()> {
+    for file in files::find_photo_files(directory, options.symlinks, exclusions) {
+        import_file(store, file.path(), options.takeout).await?;
+    }
+    Ok(())
+}
+
+/// Imports the file unless a photo with the same partial hash is already in the database. In
+/// Takeout mode, the JSON sidecar of the file is looked for next to it.
+/// Errors are logged, except the fatal database ones.
+async fn import_file(store: &Store, photo_path: &Path, takeout: bool) -> StoreResult<()> {
+    let file = match File::open(photo_path).map_err(|_| "Failed to open the file") {
+        Ok(file) => file,
+
+        Err(err) => {
+            error!("Failed to open the file {}: {}", photo_path.display(), err);
+            return Ok(());
+        }
+    };
+
+    let partial_hash = match checksum::hash_file_first_bytes(&file, PARTIAL_HASH_NBYTES) {
+        Ok(partial_hash) => partial_hash,
+
+        Err(err) => {
+            error!(
+                "Failed to calculate the partial hash of the file {}: {}",
+                photo_path.display(),
+                err
+            );
+            return Ok(());
+        }
+    };
+
+    let name = photo_path.display().to_string();
+    match already_imported(store, &name, &partial_hash).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(err) => return handle_store_error(&name, err),
+    }
+
+    info!("{} not yet in DB. Inserting...", photo_path.display());
+    let sidecar = if takeout {
+        match takeout::read_sidecar(photo_path) {
+            Some(Ok(sidecar)) => Some(sidecar),
+            Some(Err(err)) => {
+                warn!("{}", err);
+                None
+            }
+            None => {
+                warn!("No sidecar found for {}", photo_path.display());
+                None
             }
+        }
+    } else {
+        None
+    };
 
-            None => {}
+    match import_photo(
+        store,
+        photo_path,
+        partial_hash,
+        sidecar,
+        files::copy_file_to_date_folder,
+    )
+    .await
+    {
+        Ok(_) => Ok(()),
+        Err(ImportError::Store(err)) => handle_store_error(&name, err),
+        Err(err) => {
+            error!("Failed to import {}: {}", name, err);
+            Ok(())
         }
+    }
+}
 
-        info!("{} not yet in DB. Inserting...", photo_path.display());
-        if let Err(err) = import_photo(&pool, photo_path, partial_hash).await {
-            error!("Failed to import {}: {}", photo_path.display(), err);
+async fn already_imported(store: &Store, name: &str, partial_hash: &str) -> StoreResult<bool> {
+    match retry_while_busy(|| store.photo_by_partial_hash(partial_hash)).await {
+        Ok(photo_in_db) => {
+            info!(
+                "{}  already in DB (in {}/{}), skipping...",
+                name, photo_in_db.directory, photo_in_db.filename
+            );
+            Ok(true)
         }
+        Err(StoreError::NotFound) => Ok(false),
+        Err(err) => Err(err),
     }
+}
 
+/// Runs the database operation again, with an increasing delay, while the database is locked by
+/// another process.
+async fn retry_while_busy<T, F, Fut>(mut operation: F) -> StoreResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = StoreResult<T>>,
+{
+    let mut delay = BUSY_FIRST_DELAY;
+    for _ in 0..BUSY_RETRIES {
+        match operation().await {
+            Err(StoreError::Busy) => {
+                debug!("The database is locked, retrying in {:?}...", delay);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+    operation().await
+}
+
+/// Logs the database error of the import of one file, unless it makes the rest of the import
+/// pointless: the schema not being the expected one is returned.
+fn handle_store_error(name: &str, err: StoreError) -> StoreResult<()> {
+    match err {
+        StoreError::SchemaMismatch(_) => return Err(err),
+        StoreError::Busy => error!(
+            "Failed to import {}: the database stayed locked, skipping...",
+            name
+        ),
+        // the photo was most likely inserted by another import in the meantime:
+        StoreError::Constraint(message) => warn!(
+            "{} conflicts with a photo already in DB ({}), skipping...",
+            name, message
+        ),
+        err => error!("Failed to import {}: {}", name, err),
+    }
     Ok(())
 }
 
+/// Streams the members of the archive through the import, without unpacking the archive: the
+/// partial hash is computed from the first bytes of each member, and only the members not yet in
+/// the database are written, directly in the repository.
+/// The database errors ending the whole import are returned in the `Ok` value, the errors reading
+/// the archive in the `Err` one.
+async fn import_archive(
+    store: &Store,
+    archive_path: &Path,
+    format: ArchiveFormat,
+    takeout: bool,
+    exclusions: &mut Exclusions,
+) -> Result<StoreResult<()>, String> {
+    let file = File::open(archive_path).map_err(|err| err.to_string())?;
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = ZipArchive::new(file).map_err(|err| err.to_string())?;
+
+            // the sidecars are read from the archive before their media file, the members of a
+            // zip being randomly accessible:
+            let json_names: Vec<String> = if takeout {
+                zip.file_names()
+                    .filter(|name| name.ends_with(".json"))
+                    .map(String::from)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            for index in 0..zip.len() {
+                let sidecar = if takeout {
+                    read_zip_sidecar(&mut zip, index, &json_names)
+                } else {
+                    None
+                };
+
+                let member = match zip.by_index(index) {
+                    Ok(member) => member,
+                    Err(err) => {
+                        error!(
+                            "Failed to read the member #{} of {}: {}",
+                            index,
+                            archive_path.display(),
+                            err
+                        );
+                        continue;
+                    }
+                };
+                if !member.is_file() {
+                    continue;
+                }
+                // enclosed_name rejects the absolute paths and the ones escaping the archive:
+                let member_path = match member.enclosed_name() {
+                    Some(path) => path,
+                    None => {
+                        warn!(
+                            "{}!/{} has an unsafe path, skipping...",
+                            archive_path.display(),
+                            member.name()
+                        );
+                        continue;
+                    }
+                };
+                let size = member.size();
+
+                if let Err(err) = import_member(
+                    store,
+                    archive_path,
+                    &member_path,
+                    size,
+                    member,
+                    sidecar,
+                    exclusions,
+                )
+                .await
+                {
+                    return Ok(Err(err));
+                }
+            }
+        }
+
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => {
+            if takeout {
+                warn!(
+                    "The sidecars can't be paired with their media in the tar archive {}, only the EXIF is used",
+                    archive_path.display()
+                );
+            }
+
+            let reader: Box<dyn Read> = if format == ArchiveFormat::TarGz {
+                Box::new(GzDecoder::new(file))
+            } else {
+                Box::new(file)
+            };
+            let mut tar = TarArchive::new(reader);
+
+            for entry in tar.entries().map_err(|err| err.to_string())? {
+                let member = match entry {
+                    Ok(member) => member,
+                    Err(err) => {
+                        // the stream can't be trusted anymore:
+                        return Err(err.to_string());
+                    }
+                };
+                if !member.header().entry_type().is_file() {
+                    continue;
+                }
+                let member_path = match member.path() {
+                    // the `./` prefixes are dropped, for the logs:
+                    Ok(path) if is_enclosed(&path) => path
+                        .components()
+                        .filter(|component| *component != Component::CurDir)
+                        .collect::<PathBuf>(),
+                    _ => {
+                        warn!(
+                            "{}!/{} has an unsafe path, skipping...",
+                            archive_path.display(),
+                            String::from_utf8_lossy(&member.path_bytes())
+                        );
+                        continue;
+                    }
+                };
+                let size = member.size();
+
+                if let Err(err) = import_member(
+                    store,
+                    archive_path,
+                    &member_path,
+                    size,
+                    member,
+                    None,
+                    exclusions,
+                )
+                .await
+                {
+                    return Ok(Err(err));
+                }
+            }
+        }
+    }
+
+    // the extraction directory is removed if empty:
+    let _ = fs::remove_dir(files::EXTRACTION_DIRECTORY);
+
+    Ok(Ok(()))
+}
+
+// Reads the Takeout sidecar of the zip member at the given index, among the JSON members.
+fn read_zip_sidecar(
+    zip: &mut ZipArchive<File>,
+    index: usize,
+    json_names: &[String],
+) -> Option<Sidecar> {
+    let media_name = zip.name_for_index(index)?.to_string();
+    if !files::has_photo_extension(Path::new(&media_name)) {
+        return None;
+    }
+
+    // the sidecar is in the same directory as its media:
+    let (directory, file_name) = media_name.rsplit_once('/').unwrap_or(("", &media_name));
+    let prefix = if directory.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", directory)
+    };
+    let siblings = json_names
+        .iter()
+        .filter_map(|name| name.strip_prefix(&prefix))
+        .filter(|name| !name.contains('/'));
+
+    let sidecar_name = match takeout::find_sidecar_name(file_name, siblings) {
+        Some(name) => format!("{}{}", prefix, name),
+        None => {
+            warn!("No sidecar found for {}", media_name);
+            return None;
+        }
+    };
+
+    let mut data = Vec::new();
+    let result = zip
+        .by_name(&sidecar_name)
+        .map_err(|err| err.to_string())
+        .and_then(|mut member| member.read_to_end(&mut data).map_err(|err| err.to_string()))
+        .and_then(|_| takeout::parse_sidecar(&data));
+
+    match result {
+        Ok(sidecar) => Some(sidecar),
+        Err(err) => {
+            warn!("Invalid sidecar {}: {}", sidecar_name, err);
+            None
+        }
+    }
+}
+
+/// Imports one member of an archive. Errors are logged, except the fatal database ones.
+async fn import_member<R: Read>(
+    store: &Store,
+    archive_path: &Path,
+    member_path: &Path,
+    size: u64,
+    mut reader: R,
+    sidecar: Option<Sidecar>,
+    exclusions: &mut Exclusions,
+) -> StoreResult<()> {
+    // members are named like `archive.zip!/DCIM/IMG_1.JPG` in the logs:
+    let name = format!("{}!/{}", archive_path.display(), member_path.display());
+
+    if !files::has_photo_extension(member_path) || files::is_hidden(member_path) {
+        return Ok(());
+    }
+    if exclusions.is_excluded_member(member_path) {
+        return Ok(());
+    }
+
+    let mut head = Vec::new();
+    if let Err(err) = (&mut reader)
+        .take(PARTIAL_HASH_NBYTES)
+        .read_to_end(&mut head)
+    {
+        error!("Failed to read {}: {}", name, err);
+        return Ok(());
+    }
+
+    let partial_hash = match checksum::hash_first_bytes(&head[..], size, PARTIAL_HASH_NBYTES) {
+        Ok(partial_hash) => partial_hash,
+
+        Err(err) => {
+            error!("Failed to calculate the partial hash of {}: {}", name, err);
+            return Ok(());
+        }
+    };
+
+    match already_imported(store, &name, &partial_hash).await {
+        Ok(true) => return Ok(()),
+        Ok(false) => {}
+        Err(err) => return handle_store_error(&name, err),
+    }
+
+    info!("{} not yet in DB. Inserting...", name);
+    // the member is written in the repository, from where it's moved to its date folder once its
+    // metadata is read:
+    let file_name = member_path.file_name().unwrap_or_default();
+    let extracted = match files::extract_to_temp(file_name, &head, reader) {
+        Ok(path) => path,
+        Err(err) => {
+            error!("Failed to extract {}: {}", name, err);
+            return Ok(());
+        }
+    };
+
+    let result = import_photo(
+        store,
+        &extracted,
+        partial_hash,
+        sidecar,
+        files::move_file_to_date_folder,
+    )
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&extracted);
+    }
+    match result {
+        Ok(_) => Ok(()),
+        Err(ImportError::Store(err)) => handle_store_error(&name, err),
+        Err(err) => {
+            error!("Failed to import {}: {}", name, err);
+            Ok(())
+        }
+    }
+}
+
+// Tells if the path of a tar member stays inside the archive once unpacked (no root, no `..`).
+fn is_enclosed(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Reads the metadata of the file, puts the file in its date folder with the `place` function
+/// (copy or move), and inserts the photo in the database. The placed file is removed if the
+/// insertion fails. The values of the sidecar, if any, are used when the EXIF lacks them.
 async fn import_photo(
-    pool: &SqlitePool,
+    store: &Store,
     file_path: &Path,
     file_partial_hash: String,
-) -> Result<String, String> {
+    sidecar: Option<Sidecar>,
+    place: fn(&Path, &str) -> Result<(), String>,
+) -> Result<String, ImportError> {
     // The exif info we're interested in is extracted and returned in this struct:
     let pexif = photoexif::read(file_path)?;
 
+    // the derived metadata (location, video, numeric exposure, annotations...) is read before the
+    // pexif fields are moved:
+    let (latitude, longitude) = match (pexif.latitude(), pexif.longitude()) {
+        (Some(latitude), Some(longitude)) => (Some(latitude), Some(longitude)),
+        _ => (None, None),
+    };
+    let altitude = pexif.altitude();
+    let gps_direction = pexif.gps_direction();
+    let video = pexif.video();
+    let exposure_seconds = pexif.exposure_seconds();
+    let f_number = pexif.f_number();
+    let focal_length_mm = pexif.focal_length_mm();
+    let focal_length_35mm = pexif.focal_length_35mm();
+    let camera_serial = pexif.camera_serial();
+    let lens_serial = pexif.lens_serial();
+    let shutter_count = pexif.shutter_count();
+    let orientation = pexif.orientation();
+    let (display_width, display_height) = pexif.display_dimensions();
+    let rating = pexif.rating();
+    let color_label = pexif.color_label();
+    let caption = pexif.caption();
+    let tags = pexif.tags();
+    let raw_metadata = metadata::compress(&pexif.metadata)?;
+
+    let sidecar = sidecar.unwrap_or_default();
+
     // the date "YYYY-MM-DD hh:mm:ss" when the photo was taken is parsed:
-    // if no date is found, 1970-01-01 is used.
+    // if no date is found, 1970-01-01 00:00:00 is used.
     // TODO: better deal with this case:
     // - add an attribute like 'has_date' in the DB?
     // - prefix all image files with their partial hash to minimize names clashes in the 1970-01-01
     // folder (and others).
-    let long_date = photoexif::find_usable_date(pexif.date_time_original, pexif.create_date)
-        .unwrap_or_else(|| {
-            warn!(
-                "No usable date found in exif data of {}",
-                file_path.display()
-            );
-            "1970-01-01".into()
-        });
+    let create_date = photoexif::find_usable_date([
+        pexif.date_time_original,
+        pexif.create_date,
+        sidecar.taken_date,
+    ])
+    .and_then(|date| NaiveDateTime::parse_from_str(&date, "%Y-%m-%d %H:%M:%S").ok())
+    .unwrap_or_else(|| {
+        warn!(
+            "No usable date found in exif data of {}",
+            file_path.display()
+        );
+        NaiveDateTime::default()
+    });
 
-    let short_date = long_date[..10].to_string();
+    let short_date = create_date.format("%Y-%m-%d").to_string();
 
     // read the file size in bytes:
     let file_size_bytes =
@@ -93,44 +611,96 @@ async fn import_photo(
     files::create_date_folder(&short_date).map_err(|error| {
         format!(
             "Could not create a directory for the date {}: {}",
-            short_date,
-            error.to_string()
+            short_date, error
         )
     })?;
 
-    // copy the file to this folder:
-    files::copy_file_to_date_folder(file_path, &short_date)
+    // copy (or move) the file to this folder:
+    place(file_path, &short_date)
         .map_err(|error| format!("Failed to copy the file {}: {}", file_path.display(), error))?;
 
+    // the location from the EXIF has the priority over the one from the sidecar:
+    let (latitude, longitude, altitude, location_source) = if latitude.is_some() {
+        (latitude, longitude, altitude, Some("exif"))
+    } else if sidecar.latitude.is_some() && sidecar.longitude.is_some() {
+        (
+            sidecar.latitude,
+            sidecar.longitude,
+            sidecar.altitude,
+            Some("takeout"),
+        )
+    } else {
+        (None, None, None, None)
+    };
+
+    // the place names are found offline, from the location:
+    let place_names = match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => places::locate(latitude, longitude),
+        _ => Place::default(),
+    };
+
     let filename = file_path
         .file_name()
-        .unwrap()
+        .ok_or(format!("{} has no file name", file_path.display()))?
         .to_string_lossy()
         .into_owned();
 
     let new_photo = NewPhoto {
-        create_date: long_date,
+        create_date,
         filename,
         directory: short_date,
         partial_sha256_hash: file_partial_hash,
         file_size_bytes: file_size_bytes as i64,
-        image_height: pexif.image_height.map(|value| value as i32),
-        image_width: pexif.image_width.map(|value| value as i32),
+        image_height: pexif.image_height.map(i64::from),
+        image_width: pexif.image_width.map(i64::from),
         mime_type: pexif.mime_type,
-        iso: pexif.iso.map(|value| value as i32),
-        aperture: pexif.aperture,
+        iso: pexif.iso.map(i64::from),
+        aperture: pexif.aperture.map(f64::from),
         shutter_speed: pexif.shutter_speed,
         focal_length: pexif.focal_length,
+        exposure_seconds,
+        f_number,
+        focal_length_mm,
+        focal_length_35mm,
         make: pexif.make,
         model: pexif.model,
         lens_info: pexif.lens_info,
         lens_make: pexif.lens_make,
         lens_model: pexif.lens_model,
+        camera_serial,
+        lens_serial,
+        shutter_count,
+        latitude,
+        longitude,
+        altitude,
+        gps_direction,
+        location_source: location_source.map(String::from),
+        place: place_names,
+        orientation,
+        display_width,
+        display_height,
+        rating,
+        color_label,
+        // the caption of the sidecar is used when the EXIF lacks one, like the location and date:
+        caption: caption.or(sidecar.caption),
+        tags,
     };
 
-    // insertion in the database!
-    database::insert_photo(pool, new_photo)
-        .await
-        .map_err(|error| format!("Failed to insert photo into the database: {}", error))?;
+    // insertion in the database, with the video and raw metadata. The placed file, created by
+    // this import (files are never overwritten), isn't left in the repository without its row.
+    // Unless the photo was inserted by another import in the meantime: the file is then kept,
+    // for the row of that import may refer to it.
+    let inserted =
+        retry_while_busy(|| store.insert_photo(&new_photo, video.as_ref(), &raw_metadata)).await;
+    if let Err(err) = inserted {
+        if !matches!(err, StoreError::Constraint(_)) {
+            let placed = Path::new(&new_photo.directory).join(&new_photo.filename);
+            if let Err(remove_err) = fs::remove_file(&placed) {
+                error!("Failed to remove {}: {}", placed.display(), remove_err);
+            }
+        }
+        return Err(err.into());
+    }
+
     Ok(file_path.display().to_string())
 }
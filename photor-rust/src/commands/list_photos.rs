@@ -1,12 +1,110 @@
-use crate::database;
+use crate::cli::list::{ImportSession, ListArgs};
+use crate::commands::query;
+use crate::database::{ArchiveFilter, PhotoFilters, Store};
+use crate::models::{Photo, PHOTO_COLUMNS};
+use crate::output::{self, OutputFormat, Printer};
+use chrono::Duration;
+use serde_json::Value as JsonValue;
+use std::io;
+use std::path::{Path, PathBuf};
 
-pub async fn run() -> anyhow::Result<()> {
-    let pool = database::pool().await?;
-    let res = database::list_photos(&pool).await?;
+// The columns of the table format, unless others are given.
+const TABLE_COLUMNS: &[&str] = &["id", "path", "create_date", "mime_type", "file_size_bytes"];
 
-    for p in res {
-        println!("{}/{}", p.directory, p.filename);
+pub async fn run(args: &ListArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+
+    let import_id = match args.import {
+        None => None,
+        // the imports are recorded by the web app only:
+        Some(_) if !store.web_app().is_some_and(|web_app| web_app.has_imports) => {
+            anyhow::bail!(
+                "The database has no imports, they're recorded when it's shared with the web app"
+            )
+        }
+        Some(ImportSession::Id(id)) => Some(id),
+        Some(ImportSession::Last) => match store.last_import_id().await? {
+            Some(id) => Some(id),
+            None => anyhow::bail!("No import recorded yet"),
+        },
+    };
+    let archive = match (&args.in_archive, args.not_archived) {
+        (Some(archive_id), _) => ArchiveFilter::InArchive(archive_id.clone()),
+        (None, true) => ArchiveFilter::NotArchived,
+        (None, false) => ArchiveFilter::Any,
+    };
+
+    let filters = PhotoFilters {
+        near: args.near.map(|point| (point, args.radius)),
+        bbox: args.bbox,
+        place: args.place.clone(),
+        min_duration: args.min_duration.map(seconds),
+        max_duration: args.max_duration.map(seconds),
+        meta: args.where_meta.clone(),
+        tags: args.tags.clone(),
+        min_rating: args.min_rating,
+        exposure_longer_than: args.shutter_slower_than,
+        exposure_shorter_than: args.shutter_faster_than,
+        f_number: args.aperture,
+        focal_length: args.focal,
+        focal_length_35mm: args.focal_35mm,
+        from: args.from,
+        to: args.to,
+        make: args.make.clone(),
+        model: args.model.clone(),
+        lens: args.lens.clone(),
+        mime_type: args.mime_type.clone(),
+        iso: args.iso,
+        size: args.size,
+        import_id,
+        archive,
+        sort: args.sort,
+        reverse: args.reverse,
+        limit: args.limit,
+        offset: args.offset,
+        query: Some(query::load(&store, &args.query).await?),
+    };
+    let root = if args.absolute {
+        std::env::current_dir()?
+    } else {
+        PathBuf::new()
+    };
+    let format = args.output.format.unwrap_or(OutputFormat::Paths);
+    let columns: Vec<String> = if args.output.columns.is_empty() && format == OutputFormat::Table {
+        TABLE_COLUMNS
+            .iter()
+            .map(|column| column.to_string())
+            .collect()
+    } else {
+        args.output.columns.clone()
+    };
+
+    // the photos are printed as they're read:
+    let mut names = vec!["path"];
+    names.extend(PHOTO_COLUMNS);
+    let mut printer = Printer::new(io::stdout().lock(), format, &names, &columns)?;
+    let printed = store
+        .for_each_photo(&filters, |photo| {
+            let path = root.join(&photo.directory).join(&photo.filename);
+            printer.row(&row(&photo, &path)?, Some(&path))
+        })
+        .await
+        .and_then(|()| printer.finish());
+    match printed {
+        // the output was closed before the end, like by `photor list | head`:
+        Err(err) if output::is_closed(&err) => Ok(()),
+        printed => printed,
     }
+}
+
+// The columns of a photo, after the path of its file.
+fn row(photo: &Photo, path: &Path) -> anyhow::Result<Vec<JsonValue>> {
+    let values = serde_json::to_value(photo)?;
+    let mut row = vec![path.to_string_lossy().into()];
+    row.extend(PHOTO_COLUMNS.iter().map(|column| values[column].clone()));
+    Ok(row)
+}
 
-    Ok(())
+fn seconds(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.0
 }
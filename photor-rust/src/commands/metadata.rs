@@ -0,0 +1,505 @@
+use crate::cli::metadata::{MetadataCommand, RefreshArgs, RefreshField};
+use crate::database::{self, Store};
+use crate::metadata;
+use crate::models::{ColumnValue, NewVideo, Photo};
+use crate::photoexif::{self, PExif};
+use crate::places;
+use clap::ValueEnum;
+use std::path::Path;
+
+pub async fn run(command: &MetadataCommand) -> anyhow::Result<()> {
+    match command {
+        MetadataCommand::Refresh(args) => refresh(args).await,
+    }
+}
+
+// The changes to apply to a photo row.
+#[derive(Default)]
+struct Changes {
+    columns: Vec<(&'static str, ColumnValue)>,
+    video: Option<NewVideo>,
+    metadata: Option<Vec<u8>>,
+    tags: Option<Vec<String>>,
+    // what changed, to be reported:
+    descriptions: Vec<String>,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.descriptions.is_empty()
+    }
+
+    fn set_column(&mut self, column: &'static str, old: ColumnValue, new: ColumnValue) {
+        if old != new {
+            self.descriptions
+                .push(format!("{}: {} -> {}", column, old, new));
+            self.columns.push((column, new));
+        }
+    }
+}
+
+async fn refresh(args: &RefreshArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+
+    // all the fields by default, deduplicated and in a stable order, so that the options identify
+    // the run to resume:
+    let fields: Vec<RefreshField> = RefreshField::value_variants()
+        .iter()
+        .filter(|field| args.fields.is_empty() || args.fields.contains(field))
+        .copied()
+        .collect();
+    let since = args.since.map(|date| date.format("%Y-%m-%d").to_string());
+    let options = format!(
+        "fields={};since={};only_missing={}",
+        fields
+            .iter()
+            .map(|field| field_name(*field))
+            .collect::<Vec<_>>()
+            .join(","),
+        since.as_deref().unwrap_or(""),
+        args.only_missing
+    );
+
+    let missing: Vec<&str> = if args.only_missing {
+        fields
+            .iter()
+            .map(|field| missing_condition(*field))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut last_photo_id = 0;
+    if args.restart {
+        store.delete_refresh_progress(&options).await?;
+    } else if let Some(id) = store.refresh_progress(&options).await? {
+        info!("Resuming the interrupted refresh after the photo #{}", id);
+        last_photo_id = id;
+    }
+
+    let (mut checked, mut updated, mut failed) = (0, 0, 0);
+    loop {
+        let photos = store
+            .photos_to_refresh(
+                last_photo_id,
+                since.as_deref(),
+                &missing,
+                args.batch_size.max(1),
+            )
+            .await?;
+        let Some(last_photo) = photos.last() else {
+            break;
+        };
+        last_photo_id = last_photo.id;
+
+        // the files are read before the transaction, to not keep the database locked meanwhile:
+        let mut batch = Vec::new();
+        for photo in photos {
+            checked += 1;
+            let path = Path::new(&photo.directory).join(&photo.filename);
+            match photoexif::read(&path) {
+                Ok(pexif) => {
+                    let changes = find_changes(&store, &photo, &pexif, &fields).await?;
+                    if !changes.is_empty() {
+                        batch.push((photo, changes));
+                    }
+                }
+                Err(err) => {
+                    failed += 1;
+                    error!("Can't read the metadata of {}: {}", path.display(), err);
+                }
+            }
+        }
+
+        let mut tx = store.begin().await?;
+        for (photo, changes) in &batch {
+            database::update_photo_columns(&mut tx, photo.id, &changes.columns).await?;
+            if let Some(tags) = &changes.tags {
+                database::set_photo_tags(&mut tx, photo.id, tags).await?;
+            }
+            if let Some(video) = &changes.video {
+                database::save_video(&mut *tx, photo.id, video).await?;
+            }
+            if let Some(data) = &changes.metadata {
+                database::save_metadata(&mut *tx, photo.id, data).await?;
+            }
+        }
+        database::save_refresh_progress(&mut tx, &options, last_photo_id).await?;
+        tx.commit().await?;
+
+        for (photo, changes) in &batch {
+            updated += 1;
+            println!(
+                "#{} {}/{}: {}",
+                photo.id,
+                photo.directory,
+                photo.filename,
+                changes.descriptions.join(", ")
+            );
+        }
+    }
+
+    // the normalized gear names follow the raw values:
+    let gear_fields = [
+        RefreshField::Make,
+        RefreshField::Model,
+        RefreshField::LensInfo,
+        RefreshField::LensModel,
+    ];
+    if updated > 0 && fields.iter().any(|field| gear_fields.contains(field)) {
+        let mut conn = store.acquire().await?;
+        database::normalize_all_gear(&mut conn).await?;
+    }
+
+    store.delete_refresh_progress(&options).await?;
+    info!(
+        "{} photo(s) checked, {} updated, {} unreadable",
+        checked, updated, failed
+    );
+
+    Ok(())
+}
+
+async fn find_changes(
+    store: &Store,
+    photo: &Photo,
+    pexif: &PExif,
+    fields: &[RefreshField],
+) -> anyhow::Result<Changes> {
+    use ColumnValue::{Integer, Real, Text};
+
+    let mut changes = Changes::default();
+
+    for field in fields {
+        match field {
+            RefreshField::ImageHeight => changes.set_column(
+                "image_height",
+                Integer(photo.image_height),
+                Integer(pexif.image_height.map(i64::from)),
+            ),
+            RefreshField::ImageWidth => changes.set_column(
+                "image_width",
+                Integer(photo.image_width),
+                Integer(pexif.image_width.map(i64::from)),
+            ),
+            RefreshField::MimeType => changes.set_column(
+                "mime_type",
+                Text(photo.mime_type.clone()),
+                Text(pexif.mime_type.clone()),
+            ),
+            RefreshField::Iso => {
+                changes.set_column("iso", Integer(photo.iso), Integer(pexif.iso.map(i64::from)))
+            }
+            RefreshField::Aperture => changes.set_column(
+                "aperture",
+                Real(photo.aperture),
+                Real(pexif.aperture.map(f64::from)),
+            ),
+            RefreshField::ShutterSpeed => changes.set_column(
+                "shutter_speed",
+                Text(photo.shutter_speed.clone()),
+                Text(pexif.shutter_speed.clone()),
+            ),
+            RefreshField::FocalLength => changes.set_column(
+                "focal_length",
+                Text(photo.focal_length.clone()),
+                Text(pexif.focal_length.clone()),
+            ),
+            RefreshField::ExposureSeconds => changes.set_column(
+                "exposure_seconds",
+                Real(photo.exposure_seconds),
+                Real(pexif.exposure_seconds()),
+            ),
+            RefreshField::FNumber => {
+                changes.set_column("f_number", Real(photo.f_number), Real(pexif.f_number()))
+            }
+            RefreshField::FocalLengthMm => changes.set_column(
+                "focal_length_mm",
+                Real(photo.focal_length_mm),
+                Real(pexif.focal_length_mm()),
+            ),
+            RefreshField::FocalLength35mm => changes.set_column(
+                "focal_length_35mm",
+                Real(photo.focal_length_35mm),
+                Real(pexif.focal_length_35mm()),
+            ),
+            RefreshField::Make => {
+                changes.set_column("make", Text(photo.make.clone()), Text(pexif.make.clone()))
+            }
+            RefreshField::Model => changes.set_column(
+                "model",
+                Text(photo.model.clone()),
+                Text(pexif.model.clone()),
+            ),
+            RefreshField::LensInfo => changes.set_column(
+                "lens_info",
+                Text(photo.lens_info.clone()),
+                Text(pexif.lens_info.clone()),
+            ),
+            RefreshField::LensMake => changes.set_column(
+                "lens_make",
+                Text(photo.lens_make.clone()),
+                Text(pexif.lens_make.clone()),
+            ),
+            RefreshField::LensModel => changes.set_column(
+                "lens_model",
+                Text(photo.lens_model.clone()),
+                Text(pexif.lens_model.clone()),
+            ),
+            RefreshField::CameraSerial => changes.set_column(
+                "camera_serial",
+                Text(photo.camera_serial.clone()),
+                Text(pexif.camera_serial()),
+            ),
+            RefreshField::LensSerial => changes.set_column(
+                "lens_serial",
+                Text(photo.lens_serial.clone()),
+                Text(pexif.lens_serial()),
+            ),
+            RefreshField::ShutterCount => changes.set_column(
+                "shutter_count",
+                Integer(photo.shutter_count),
+                Integer(pexif.shutter_count()),
+            ),
+            RefreshField::Orientation => changes.set_column(
+                "orientation",
+                Integer(photo.orientation),
+                Integer(pexif.orientation()),
+            ),
+            RefreshField::Rating => {
+                changes.set_column("rating", Integer(photo.rating), Integer(pexif.rating()))
+            }
+            RefreshField::ColorLabel => changes.set_column(
+                "color_label",
+                Text(photo.color_label.clone()),
+                Text(pexif.color_label()),
+            ),
+            RefreshField::Caption => {
+                if let Some(caption) = pexif.caption() {
+                    changes.set_column("caption", Text(photo.caption.clone()), Text(Some(caption)));
+                }
+            }
+            RefreshField::Tags => {
+                let tags = pexif.tags();
+                if store.photo_tags(photo.id).await? != tags {
+                    changes
+                        .descriptions
+                        .push(format!("tags: {}", tags.join(", ")));
+                    changes.tags = Some(tags);
+                }
+            }
+            RefreshField::Location => location_changes(&mut changes, photo, pexif),
+            RefreshField::Video => {
+                if let Some(video) = pexif.video() {
+                    let current = store.video_by_photo_id(photo.id).await?;
+                    if current.map(|current| current.to_new_video()).as_ref() != Some(&video) {
+                        changes.descriptions.push("video".to_string());
+                        changes.video = Some(video);
+                    }
+                }
+            }
+            RefreshField::Metadata => {
+                let data = metadata::compress(&pexif.metadata).map_err(anyhow::Error::msg)?;
+                let current = store.metadata_by_photo_id(photo.id).await?;
+                if current.as_ref() != Some(&data) {
+                    changes.descriptions.push("metadata".to_string());
+                    changes.metadata = Some(data);
+                }
+            }
+        }
+    }
+
+    // the displayed dimensions follow the dimensions and the orientation:
+    let dimension_fields = [
+        RefreshField::ImageWidth,
+        RefreshField::ImageHeight,
+        RefreshField::Orientation,
+    ];
+    if fields.iter().any(|field| dimension_fields.contains(field)) {
+        let (display_width, display_height) = pexif.display_dimensions();
+        changes.set_column(
+            "display_width",
+            Integer(photo.display_width),
+            Integer(display_width),
+        );
+        changes.set_column(
+            "display_height",
+            Integer(photo.display_height),
+            Integer(display_height),
+        );
+    }
+
+    Ok(changes)
+}
+
+// The locations set by a geotag or from a Takeout sidecar aren't replaced by the EXIF one. The
+// locations of unknown source (set before the source was recorded) are only replaced by an EXIF
+// position, never cleared.
+fn location_changes(changes: &mut Changes, photo: &Photo, pexif: &PExif) {
+    use ColumnValue::{Real, Text};
+
+    if photo
+        .location_source
+        .as_deref()
+        .is_some_and(|source| source != "exif")
+    {
+        return;
+    }
+
+    let (latitude, longitude) = match (pexif.latitude(), pexif.longitude()) {
+        (Some(latitude), Some(longitude)) => (Some(latitude), Some(longitude)),
+        _ => (None, None),
+    };
+    if photo.location_source.is_none() && photo.latitude.is_some() && latitude.is_none() {
+        return;
+    }
+    let position_changed = (latitude, longitude) != (photo.latitude, photo.longitude);
+
+    changes.set_column("latitude", Real(photo.latitude), Real(latitude));
+    changes.set_column("longitude", Real(photo.longitude), Real(longitude));
+    changes.set_column("altitude", Real(photo.altitude), Real(pexif.altitude()));
+    changes.set_column(
+        "gps_direction",
+        Real(photo.gps_direction),
+        Real(pexif.gps_direction()),
+    );
+
+    if position_changed {
+        let source = latitude.map(|_| "exif".to_string());
+        let place = latitude
+            .zip(longitude)
+            .map(|(latitude, longitude)| places::locate(latitude, longitude))
+            .unwrap_or_default();
+
+        changes.set_column(
+            "location_source",
+            Text(photo.location_source.clone()),
+            Text(source),
+        );
+        changes.set_column(
+            "country_code",
+            Text(photo.country_code.clone()),
+            Text(place.country_code),
+        );
+        changes.set_column("country", Text(photo.country.clone()), Text(place.country));
+        changes.set_column("region", Text(photo.region.clone()), Text(place.region));
+        changes.set_column("city", Text(photo.city.clone()), Text(place.city));
+    }
+}
+
+fn field_name(field: RefreshField) -> String {
+    field
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+// The SQL condition selecting the photos missing the field.
+fn missing_condition(field: RefreshField) -> &'static str {
+    match field {
+        RefreshField::ImageHeight => "image_height IS NULL",
+        RefreshField::ImageWidth => "image_width IS NULL",
+        RefreshField::MimeType => "mime_type IS NULL",
+        RefreshField::Iso => "iso IS NULL",
+        RefreshField::Aperture => "aperture IS NULL",
+        RefreshField::ShutterSpeed => "shutter_speed IS NULL",
+        RefreshField::FocalLength => "focal_length IS NULL",
+        RefreshField::ExposureSeconds => "exposure_seconds IS NULL",
+        RefreshField::FNumber => "f_number IS NULL",
+        RefreshField::FocalLengthMm => "focal_length_mm IS NULL",
+        RefreshField::FocalLength35mm => "focal_length_35mm IS NULL",
+        RefreshField::Make => "make IS NULL",
+        RefreshField::Model => "model IS NULL",
+        RefreshField::LensInfo => "lens_info IS NULL",
+        RefreshField::LensMake => "lens_make IS NULL",
+        RefreshField::LensModel => "lens_model IS NULL",
+        RefreshField::CameraSerial => "camera_serial IS NULL",
+        RefreshField::LensSerial => "lens_serial IS NULL",
+        RefreshField::ShutterCount => "shutter_count IS NULL",
+        RefreshField::Orientation => "orientation IS NULL",
+        RefreshField::Rating => "rating IS NULL",
+        RefreshField::ColorLabel => "color_label IS NULL",
+        RefreshField::Caption => "caption IS NULL",
+        RefreshField::Tags => "id NOT IN (SELECT photo_id FROM photo_tags)",
+        RefreshField::Location => "latitude IS NULL",
+        RefreshField::Video => {
+            "(mime_type LIKE 'video/%' AND id NOT IN (SELECT photo_id FROM videos))"
+        }
+        RefreshField::Metadata => "id NOT IN (SELECT photo_id FROM photo_metadata)",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PHOTO_COLUMNS;
+
+    #[test]
+    fn test_set_column_unchanged() {
+        let mut changes = Changes::default();
+        changes.set_column(
+            "iso",
+            ColumnValue::Integer(Some(100)),
+            ColumnValue::Integer(Some(100)),
+        );
+        changes.set_column("altitude", ColumnValue::Real(None), ColumnValue::Real(None));
+        changes.set_column(
+            "caption",
+            ColumnValue::Text(Some("Lake".to_string())),
+            ColumnValue::Text(Some("Lake".to_string())),
+        );
+
+        assert!(changes.is_empty());
+        assert!(changes.columns.is_empty());
+    }
+
+    #[test]
+    fn test_set_column_changed() {
+        let mut changes = Changes::default();
+        changes.set_column(
+            "iso",
+            ColumnValue::Integer(None),
+            ColumnValue::Integer(Some(200)),
+        );
+        changes.set_column(
+            "caption",
+            ColumnValue::Text(Some("Lake".to_string())),
+            ColumnValue::Text(None),
+        );
+
+        assert_eq!(
+            changes.descriptions,
+            vec!["iso: NULL -> 200", "caption: \"Lake\" -> NULL"]
+        );
+        assert_eq!(
+            changes.columns,
+            vec![
+                ("iso", ColumnValue::Integer(Some(200))),
+                ("caption", ColumnValue::Text(None)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_condition() {
+        for field in RefreshField::value_variants() {
+            let condition = missing_condition(*field);
+            // the column conditions name a column of the photos, the other ones a related table:
+            match condition.strip_suffix(" IS NULL") {
+                Some(column) => {
+                    assert!(PHOTO_COLUMNS.contains(&column), "unknown column {}", column)
+                }
+                None => assert!(condition.contains("SELECT photo_id FROM ")),
+            }
+        }
+
+        assert_eq!(
+            missing_condition(RefreshField::Location),
+            "latitude IS NULL"
+        );
+        assert_eq!(
+            missing_condition(RefreshField::Tags),
+            "id NOT IN (SELECT photo_id FROM photo_tags)"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::cli::migrate::{MigrateArgs, MigrateCommand, MigrateStatusArgs};
+use crate::commands::db::{self, BACKUP_DIRECTORY};
+use crate::database::Store;
+use crate::output::{self, Listing, OutputFormat};
+use crate::schema::{MigrationState, MigrationStatus};
+use std::path::Path;
+
+pub async fn run(args: &MigrateArgs) -> anyhow::Result<()> {
+    // the schema isn't checked, changing it being the point:
+    let store = Store::connect().await?;
+    let migrations = store.migrations().await?;
+
+    match &args.command {
+        None | Some(MigrateCommand::Up) => {
+            // the migrations modified or failed aren't applied again by sqlx:
+            if let Some(problem) = migrations.iter().find_map(|migration| migration.problem()) {
+                anyhow::bail!("The database can't be migrated: {}", problem);
+            }
+
+            let pending = migrations
+                .iter()
+                .filter(|migration| migration.state == MigrationState::Pending)
+                .count();
+            if pending == 0 {
+                info!("The database is up to date");
+                return Ok(());
+            }
+
+            if !args.no_backup {
+                backup(&store, &migrations, "before-migrate").await?;
+            }
+            store.migrate().await?;
+            info!("{} migration(s) applied", pending);
+        }
+
+        Some(MigrateCommand::Status(status_args)) => status(&migrations, status_args)?,
+
+        Some(MigrateCommand::Down(down_args)) => {
+            // the migrations applied by a newer version have no down file here:
+            let applied: Vec<i64> = migrations
+                .iter()
+                .filter(|migration| {
+                    !matches!(
+                        migration.state,
+                        MigrationState::Pending | MigrationState::Unknown
+                    )
+                })
+                .map(|migration| migration.version)
+                .collect();
+
+            let target = match down_args.to {
+                Some(0) => 0,
+                Some(version) => {
+                    if !migrations
+                        .iter()
+                        .any(|migration| migration.version == version)
+                    {
+                        anyhow::bail!("No migration with the version {}", version);
+                    }
+                    version
+                }
+                // the last one only:
+                None => applied.iter().rev().nth(1).copied().unwrap_or(0),
+            };
+
+            if let Some(unknown) = migrations.iter().find(|migration| {
+                migration.state == MigrationState::Unknown && migration.version > target
+            }) {
+                anyhow::bail!(
+                    "The migration {} was applied by a newer version of photor, which must revert it",
+                    unknown.version
+                );
+            }
+
+            let reverted = applied.iter().filter(|version| **version > target).count();
+            if reverted == 0 {
+                info!("No migration to revert");
+                return Ok(());
+            }
+
+            if !args.no_backup {
+                backup(&store, &migrations, "before-revert").await?;
+            }
+            store.revert(target).await?;
+            info!("{} migration(s) reverted", reverted);
+        }
+    }
+
+    Ok(())
+}
+
+fn status(migrations: &[MigrationStatus], args: &MigrateStatusArgs) -> anyhow::Result<()> {
+    let mut listing = Listing::new(&[
+        "version",
+        "state",
+        "checksum",
+        "installed_on",
+        "description",
+    ]);
+    for migration in migrations {
+        // the first bytes of the SHA-384 are enough to tell the files apart:
+        let checksum: String = migration
+            .checksum
+            .iter()
+            .take(8)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        listing.rows.push(vec![
+            migration.version.into(),
+            migration.state.to_string().into(),
+            checksum.into(),
+            migration.installed_on.map(|date| date.to_string()).into(),
+            migration.description.clone().into(),
+        ]);
+    }
+
+    let format = args.output.format.unwrap_or(OutputFormat::Table);
+    output::print(listing, format, &args.output.columns)?;
+
+    if format == OutputFormat::Table {
+        let pending = migrations
+            .iter()
+            .filter(|migration| migration.state == MigrationState::Pending)
+            .count();
+        println!("{} migration(s), {} pending", migrations.len(), pending);
+    }
+    Ok(())
+}
+
+// Copies the database in the backups directory, unless it was never migrated (nothing to lose).
+async fn backup(store: &Store, migrations: &[MigrationStatus], reason: &str) -> anyhow::Result<()> {
+    if migrations
+        .iter()
+        .all(|migration| migration.state == MigrationState::Pending)
+    {
+        return Ok(());
+    }
+
+    let path = db::backup_to_directory(store, Path::new(BACKUP_DIRECTORY), Some(reason)).await?;
+    info!("Database backed up to {}", path.display());
+
+    Ok(())
+}
@@ -1,2 +1,13 @@
+pub mod archive;
+pub mod catalog;
+pub mod db;
+pub mod gear;
+pub mod geotag;
 pub mod import;
 pub mod list_photos;
+pub mod metadata;
+pub mod migrate;
+pub mod places;
+pub mod query;
+pub mod show;
+pub mod stats;
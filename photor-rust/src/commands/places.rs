@@ -0,0 +1,63 @@
+use crate::cli::places::{PlaceLevel, PlacesArgs};
+use crate::database::{self, Store};
+use crate::output::{self, Listing, OutputFormat};
+use crate::places;
+
+// how many photos are located in a transaction by --refresh:
+const REFRESH_BATCH_SIZE: u32 = 1000;
+
+pub async fn run(args: &PlacesArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+
+    if args.refresh {
+        // the photos are read and updated by batches, to not load them all:
+        let (mut last_photo_id, mut located) = (0, 0);
+        loop {
+            let photos = store
+                .photos_without_place(last_photo_id, REFRESH_BATCH_SIZE)
+                .await?;
+            let Some(last_photo) = photos.last() else {
+                break;
+            };
+            last_photo_id = last_photo.id;
+
+            let mut tx = store.begin().await?;
+            for photo in &photos {
+                if let (Some(latitude), Some(longitude)) = (photo.latitude, photo.longitude) {
+                    let place = places::locate(latitude, longitude);
+                    // no place is found far from the cities (at sea):
+                    if place.country_code.is_some() {
+                        located += 1;
+                    }
+                    database::set_photo_place(&mut tx, photo.id, &place).await?;
+                }
+            }
+            tx.commit().await?;
+        }
+        info!("Places found for {} photo(s)", located);
+    }
+
+    let level = match args.by {
+        PlaceLevel::Country => 1,
+        PlaceLevel::Region => 2,
+        PlaceLevel::City => 3,
+    };
+    let counts = store.count_photos_by_place(level).await?;
+
+    // the photos without place are counted in a row without country:
+    let mut listing = Listing::new(&["photos", "country", "region", "city"]);
+    for count in counts {
+        listing.rows.push(vec![
+            count.count.into(),
+            count.country.into(),
+            count.region.into(),
+            count.city.into(),
+        ]);
+    }
+
+    output::print(
+        listing,
+        args.output.format.unwrap_or(OutputFormat::Table),
+        &args.output.columns,
+    )
+}
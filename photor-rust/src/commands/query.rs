@@ -0,0 +1,82 @@
+use crate::cli::query::{QueryArgs, QueryCommand, QueryDeleteArgs, QueryListArgs, QuerySaveArgs};
+use crate::database::Store;
+use crate::output::{self, Listing, OutputFormat};
+use crate::query::{self, Query};
+use std::collections::HashMap;
+
+pub async fn run(args: &QueryArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+    match &args.command {
+        QueryCommand::Save(save_args) => save(&store, save_args).await,
+        QueryCommand::List(list_args) => list(&store, list_args).await,
+        QueryCommand::Delete(delete_args) => delete(&store, delete_args).await,
+    }
+}
+
+/// Parses a query given on the command line (its words being joined), and replaces the saved
+/// queries it refers to. The `import:` terms are refused when the database has no imports.
+pub async fn load(store: &Store, words: &[String]) -> anyhow::Result<Query> {
+    let text = words.join(" ");
+    let parsed = query::parse(&text).map_err(|err| anyhow::anyhow!("Invalid query: {}", err))?;
+
+    let saved: HashMap<String, String> = store
+        .saved_queries()
+        .await?
+        .into_iter()
+        .map(|saved| (saved.name, saved.query))
+        .collect();
+    let query =
+        query::expand(&parsed, &saved).map_err(|err| anyhow::anyhow!("Invalid query: {}", err))?;
+
+    // the imports are recorded by the web app only:
+    if query.selects_imports() && !store.web_app().is_some_and(|web_app| web_app.has_imports) {
+        anyhow::bail!(
+            "The database has no imports, they're recorded when it's shared with the web app"
+        )
+    }
+    Ok(query)
+}
+
+async fn save(store: &Store, args: &QuerySaveArgs) -> anyhow::Result<()> {
+    query::check_name(&args.name).map_err(|err| anyhow::anyhow!(err))?;
+
+    // the query is checked, with the saved queries it refers to, but stored as written:
+    let text = args.query.join(" ");
+    let parsed = query::parse(&text).map_err(|err| anyhow::anyhow!("Invalid query: {}", err))?;
+    let mut saved: HashMap<String, String> = store
+        .saved_queries()
+        .await?
+        .into_iter()
+        .map(|saved| (saved.name, saved.query))
+        .collect();
+    saved.insert(args.name.clone(), text.clone());
+    query::expand(&parsed, &saved).map_err(|err| anyhow::anyhow!("Invalid query: {}", err))?;
+
+    store.save_query(&args.name, &text).await?;
+    info!("Query @{} saved", args.name);
+    Ok(())
+}
+
+async fn list(store: &Store, args: &QueryListArgs) -> anyhow::Result<()> {
+    let mut listing = Listing::new(&["name", "query", "updated_at"]);
+    for saved in store.saved_queries().await? {
+        listing.rows.push(vec![
+            saved.name.into(),
+            saved.query.into(),
+            saved.updated_at.to_string().into(),
+        ]);
+    }
+    output::print(
+        listing,
+        args.output.format.unwrap_or(OutputFormat::Table),
+        &args.output.columns,
+    )
+}
+
+async fn delete(store: &Store, args: &QueryDeleteArgs) -> anyhow::Result<()> {
+    if !store.delete_query(&args.name).await? {
+        anyhow::bail!("No saved query named @{}", args.name);
+    }
+    info!("Query @{} deleted", args.name);
+    Ok(())
+}
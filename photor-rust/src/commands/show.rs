@@ -0,0 +1,205 @@
+use crate::checksum;
+use crate::cli::show::ShowArgs;
+use crate::commands::import::PARTIAL_HASH_NBYTES;
+use crate::database::{Store, StoreError, StoreResult};
+use crate::models::{Photo, PHOTO_COLUMNS};
+use crate::output;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+// where the web app writes the thumbnails, in the repository:
+const THUMBNAILS_DIRECTORY: &str = "thumbnails";
+
+pub async fn run(args: &ShowArgs) -> anyhow::Result<()> {
+    let store = Store::open().await?;
+    let repository = std::env::current_dir()?;
+
+    let photo = find(&store, &repository, &args.photo).await?;
+    print(&store, &repository, &photo).await
+}
+
+// The photo given by its id, its partial hash or the path of its file in the repository. Any
+// other file is looked up by its partial hash, like when it's imported.
+async fn find(store: &Store, repository: &Path, given: &str) -> anyhow::Result<Photo> {
+    lazy_static! {
+        static ref HASH_RE: Regex = Regex::new(r"^[0-9a-f]{64}$").unwrap();
+    }
+
+    if let Ok(id) = given.parse::<i64>() {
+        if let Some(photo) = found(store.photo_by_id(id).await)? {
+            return Ok(photo);
+        }
+    }
+    if HASH_RE.is_match(given) {
+        if let Some(photo) = found(store.photo_by_partial_hash(given).await)? {
+            return Ok(photo);
+        }
+    }
+
+    let path = repository.join(given);
+    // the file of a photo is found by its path even if it's missing:
+    if let Some((directory, filename)) = repository_path(repository, &path) {
+        if let Some(photo) = found(store.photo_by_path(&directory, &filename).await)? {
+            return Ok(photo);
+        }
+    }
+    if !path.is_file() {
+        anyhow::bail!("No photo matches {}", given);
+    }
+
+    let hash = checksum::hash_file_first_bytes(&File::open(&path)?, PARTIAL_HASH_NBYTES)
+        .map_err(|err| anyhow::anyhow!("Failed to hash {}: {}", path.display(), err))?;
+    match found(store.photo_by_partial_hash(&hash).await)? {
+        Some(photo) => {
+            println!(
+                "{} is already in the repository, as {}",
+                given,
+                Path::new(&photo.directory).join(&photo.filename).display()
+            );
+            println!();
+            Ok(photo)
+        }
+        None => anyhow::bail!("{} isn't in the repository (partial hash {})", given, hash),
+    }
+}
+
+fn found(result: StoreResult<Photo>) -> anyhow::Result<Option<Photo>> {
+    match result {
+        Ok(photo) => Ok(Some(photo)),
+        Err(StoreError::NotFound) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// The directory and file name of a path in the repository, as stored in the database.
+fn repository_path(repository: &Path, path: &Path) -> Option<(String, String)> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let repository = fs::canonicalize(repository).unwrap_or_else(|_| repository.to_path_buf());
+    let relative = path.strip_prefix(repository).ok()?;
+
+    let filename = relative.file_name()?.to_str()?.to_string();
+    let directory = relative.parent()?.to_str()?.to_string();
+    Some((directory, filename))
+}
+
+async fn print(store: &Store, repository: &Path, photo: &Photo) -> anyhow::Result<()> {
+    let values = serde_json::to_value(photo)?;
+    for column in PHOTO_COLUMNS {
+        print_field(column, &output::text(&values[column], "-"));
+    }
+    println!();
+
+    let path = repository.join(&photo.directory).join(&photo.filename);
+    print_field("path", &path.display().to_string());
+    let file = match fs::metadata(&path) {
+        Ok(metadata) if metadata.len() as i64 == photo.file_size_bytes => {
+            format!("present, {} bytes", metadata.len())
+        }
+        Ok(metadata) => format!(
+            "present, {} bytes instead of {}",
+            metadata.len(),
+            photo.file_size_bytes
+        ),
+        Err(_) => "missing".to_string(),
+    };
+    print_field("file", &file);
+
+    let import = match store.photo_import(photo.id).await? {
+        Some(import) => format!("{} (started at {})", import.id, import.started_at),
+        None => "-".to_string(),
+    };
+    print_field("import", &import);
+
+    let archives: Vec<String> = store
+        .photo_archives(photo.id)
+        .await?
+        .iter()
+        .map(|archive| {
+            format!(
+                "{} ({}), added at {}",
+                archive.archive_id, archive.media_type, archive.inserted_at
+            )
+        })
+        .collect();
+    print_list("archives", &archives);
+
+    let tags = store.photo_tags(photo.id).await?;
+    if tags.is_empty() {
+        print_field("tags", "-");
+    } else {
+        print_field("tags", &tags.join(", "));
+    }
+
+    let thumbnails: Vec<String> = store
+        .photo_thumbnails(photo.id)
+        .await?
+        .iter()
+        .map(|thumbnail| {
+            let path = thumbnail_path(photo, &thumbnail.size_name);
+            let missing = if repository.join(&path).exists() {
+                ""
+            } else {
+                " (missing)"
+            };
+            format!(
+                "{} {}x{} {}{}",
+                thumbnail.size_name,
+                thumbnail.width,
+                thumbnail.height,
+                path.display(),
+                missing
+            )
+        })
+        .collect();
+    print_list("thumbnails", &thumbnails);
+
+    Ok(())
+}
+
+fn print_field(name: &str, value: &str) {
+    println!("{:<20}{}", name, value);
+}
+
+// The values one per line, the first one after the name.
+fn print_list(name: &str, values: &[String]) {
+    if values.is_empty() {
+        print_field(name, "-");
+    }
+    for (i, value) in values.iter().enumerate() {
+        print_field(if i == 0 { name } else { "" }, value);
+    }
+}
+
+// The path of a thumbnail in the repository, as made by the web app: `IMG_1.JPG` of `2023-05-04`
+// has `thumbnails/2023-05-04/IMG_1_small.JPG`.
+fn thumbnail_path(photo: &Photo, size_name: &str) -> PathBuf {
+    let filename = Path::new(&photo.filename);
+    let stem = filename.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match filename.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, size_name, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, size_name),
+    };
+    Path::new(THUMBNAILS_DIRECTORY)
+        .join(&photo.directory)
+        .join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thumbnail_path() {
+        let photo = Photo {
+            directory: "2023-05-04".to_string(),
+            filename: "IMG_1.JPG".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            thumbnail_path(&photo, "small"),
+            Path::new("thumbnails/2023-05-04/IMG_1_small.JPG")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::database::Store;
+
+pub async fn run() -> anyhow::Result<()> {
+    let store = Store::open().await?;
+
+    let stats = store.stats().await?;
+    println!("{:<16}{}", "Photos:", stats.photos - stats.videos);
+    println!("{:<16}{}", "Videos:", stats.videos);
+    println!(
+        "{:<16}{}",
+        "Total size:",
+        format_size(stats.total_size_bytes)
+    );
+    println!(
+        "{:<16}{}",
+        "Video duration:",
+        format_duration(stats.total_video_seconds)
+    );
+
+    let codecs = store.count_videos_by_codec().await?;
+    if !codecs.is_empty() {
+        println!();
+        println!("{:>8}  {:>10}  CODEC", "VIDEOS", "DURATION");
+        for codec in codecs {
+            println!(
+                "{:>8}  {:>10}  {}",
+                codec.count,
+                format_duration(codec.duration_seconds),
+                codec.video_codec.as_deref().unwrap_or("(unknown)")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn format_size(bytes: i64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, units[unit])
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as i64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -1,94 +1,2175 @@
-use crate::models::{NewPhoto, Photo};
-use anyhow::Result;
-use sqlx::sqlite::SqlitePool;
+use crate::catalog::{self, Catalog, Record, RecordType};
+use crate::exposure::Range;
+use crate::gear::{Gear, GearKind};
+use crate::gps::{BoundingBox, Point};
+use crate::metadata::{self, MetaCondition, MetaOperator};
+use crate::models::{
+    BodyUsage, CatalogCounts, CodecCount, ColumnValue, GearCount, MigrationRecord, NewPhoto,
+    NewVideo, Photo, PhotoArchive, PhotoFile, PhotoImport, PlaceCount, SavedQuery, Stats,
+    Thumbnail, Video, YearUsage,
+};
+use crate::places::Place;
+use crate::query::{Condition, GearField, MediaType, NumberField, Query, Value as QueryValue};
+use crate::schema::{self, MigrationStatus, VersionCheck, WebAppSchema};
+use chrono::{NaiveDate, NaiveDateTime};
+use futures_util::TryStreamExt;
+use serde_json::Value as JsonValue;
+use sqlx::error::ErrorKind;
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::sqlite::{
+    Sqlite, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqliteLockingMode,
+    SqlitePool, SqlitePoolOptions, SqliteRow,
+};
+use sqlx::{Column, Executor, QueryBuilder, Row, TypeInfo, ValueRef};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
 
-pub async fn pool() -> Result<SqlitePool> {
-    let pool = SqlitePool::connect("sqlite:db.sqlite").await?;
-    Ok(pool)
+// The primary result codes of SQLite telling that the database is locked by another connection.
+const SQLITE_BUSY: i64 = 5;
+const SQLITE_LOCKED: i64 = 6;
+
+// The migrations of photor, embedded at build time.
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// How long a connection waits for the lock of another one (like the web app's) before failing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The errors of the database, sorted by what the callers can do about them.
+#[derive(Debug)]
+pub enum StoreError {
+    /// The database stayed locked by another connection or process.
+    Busy,
+    /// A constraint (unique, foreign key, not null...) was violated.
+    Constraint(String),
+    /// The row looked for doesn't exist.
+    NotFound,
+    /// The database doesn't have the schema expected by this version of photor: migrations are
+    /// missing, or it was migrated by a newer version.
+    SchemaMismatch(String),
+    /// Any other error: I/O, corrupt database...
+    Other(sqlx::Error),
+}
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Busy => write!(f, "the database is locked by another process"),
+            StoreError::Constraint(message) => write!(f, "constraint violated: {}", message),
+            StoreError::NotFound => write!(f, "not found in the database"),
+            StoreError::SchemaMismatch(message) => {
+                write!(f, "unexpected database schema: {}", message)
+            }
+            StoreError::Other(err) => write!(f, "database error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Other(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => StoreError::NotFound,
+            sqlx::Error::ColumnNotFound(column) => {
+                StoreError::SchemaMismatch(format!("no column {}", column))
+            }
+            sqlx::Error::ColumnDecode { index, source } => {
+                StoreError::SchemaMismatch(format!("column {}: {}", index, source))
+            }
+            sqlx::Error::Migrate(err) => StoreError::from(*err),
+            sqlx::Error::Database(db_err) => {
+                // the extended result codes keep the primary one in their lowest byte:
+                let code = db_err
+                    .code()
+                    .and_then(|code| code.parse::<i64>().ok())
+                    .map(|code| code & 0xff);
+                let message = db_err.message().to_string();
+
+                if matches!(code, Some(SQLITE_BUSY) | Some(SQLITE_LOCKED)) {
+                    StoreError::Busy
+                } else if !matches!(db_err.kind(), ErrorKind::Other) {
+                    StoreError::Constraint(message)
+                } else if message.starts_with("no such table")
+                    || message.starts_with("no such column")
+                {
+                    StoreError::SchemaMismatch(message)
+                } else {
+                    StoreError::Other(sqlx::Error::Database(db_err))
+                }
+            }
+            err => StoreError::Other(err),
+        }
+    }
+}
+
+impl From<MigrateError> for StoreError {
+    fn from(err: MigrateError) -> Self {
+        match err {
+            MigrateError::Execute(err) => StoreError::from(err),
+            MigrateError::VersionMissing(version) => StoreError::SchemaMismatch(format!(
+                "the migration {} applied to the database is unknown to this version",
+                version
+            )),
+            MigrateError::VersionMismatch(version) => StoreError::SchemaMismatch(format!(
+                "the migration {} applied to the database was modified",
+                version
+            )),
+            err => StoreError::Other(sqlx::Error::Migrate(Box::new(err))),
+        }
+    }
+}
+
+/// The photos database of the repository (`db.sqlite` in the current directory). It can be
+/// shared with the web app, which adds its own tables and migrations.
+pub struct Store {
+    pool: SqlitePool,
+    web_app: Option<WebAppSchema>,
+    // the import of the web app the photos inserted are linked to:
+    import_id: Option<i64>,
+}
+
+impl Store {
+    /// Opens the database, checking that its schema is the one expected by this version.
+    pub async fn open() -> StoreResult<Self> {
+        let mut store = Self::connect().await?;
+        store.check_schema().await?;
+        Ok(store)
+    }
+
+    /// Opens the database without checking its schema, to migrate it.
+    pub async fn connect() -> StoreResult<Self> {
+        // WAL lets the web app read while photor writes, and the other way around:
+        let options = SqliteConnectOptions::new()
+            .filename("db.sqlite")
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT);
+
+        let pool = SqlitePoolOptions::new()
+            .after_connect(|conn, _meta| {
+                Box::pin(async move { metadata::register_functions(conn).await })
+            })
+            .connect_with(options)
+            .await?;
+        Ok(Store {
+            pool,
+            web_app: None,
+            import_id: None,
+        })
+    }
+
+    /// Opens the database for this process only, without checking its schema: the WAL is written
+    /// to the database file, and no other connection (like the web app's) can read or write the
+    /// database until the store is closed. Fails with `StoreError::Busy` if the database is open
+    /// elsewhere.
+    pub async fn connect_exclusive() -> StoreResult<Self> {
+        // leaving the WAL mode needs all the other connections to be closed, and the exclusive
+        // locking mode keeps the locks taken by a transaction once it's over:
+        let options = SqliteConnectOptions::new()
+            .filename("db.sqlite")
+            .locking_mode(SqliteLockingMode::Exclusive)
+            .journal_mode(SqliteJournalMode::Delete)
+            .busy_timeout(Duration::ZERO);
+
+        // a single connection, never closed before the store, holds the lock:
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .after_connect(|conn, _meta| {
+                Box::pin(async move { metadata::register_functions(conn).await })
+            })
+            .connect_with(options)
+            .await?;
+
+        let journal_mode: String = sqlx::query_scalar("pragma journal_mode")
+            .fetch_one(&pool)
+            .await?;
+        if !journal_mode.eq_ignore_ascii_case("delete") {
+            pool.close().await;
+            return Err(StoreError::Busy);
+        }
+        sqlx::query("begin exclusive").execute(&pool).await?;
+        sqlx::query("commit").execute(&pool).await?;
+
+        Ok(Store {
+            pool,
+            web_app: None,
+            import_id: None,
+        })
+    }
+
+    /// Opens a copy of the database (like a backup) read only, without checking its schema.
+    pub async fn open_copy(path: &Path) -> StoreResult<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true)
+            .busy_timeout(BUSY_TIMEOUT);
+
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Ok(Store {
+            pool,
+            web_app: None,
+            import_id: None,
+        })
+    }
+
+    /// Closes the connections, writing the WAL to the database file.
+    pub async fn close(self) {
+        self.pool.close().await;
+    }
+
+    /// Applies the pending migrations.
+    pub async fn migrate(&self) -> StoreResult<()> {
+        MIGRATOR.run(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Reverts the migrations applied after the `target` version, with their down files.
+    pub async fn revert(&self, target: i64) -> StoreResult<()> {
+        MIGRATOR.undo(&self.pool, target).await?;
+        Ok(())
+    }
+
+    /// The migrations of this version and of the database.
+    pub async fn migrations(&self) -> StoreResult<Vec<MigrationStatus>> {
+        let migrated: bool = sqlx::query_scalar(
+            "select exists (select 1 from sqlite_master where name = '_sqlx_migrations')",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let applied = if migrated {
+            sqlx::query_as::<_, MigrationRecord>(
+                r#"
+                select version, description, installed_on, success, checksum
+                from _sqlx_migrations
+                order by version
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        Ok(schema::migration_statuses(&MIGRATOR.migrations, &applied))
+    }
+
+    /// Writes a copy of the database to `dest`, which must not exist. The copy is consistent even
+    /// if the database is being written.
+    pub async fn backup(&self, dest: &Path) -> StoreResult<()> {
+        sqlx::query("vacuum into ?1")
+            .bind(dest.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// The problems found by SQLite in the database file: none when it's sound.
+    pub async fn integrity_check(&self) -> StoreResult<Vec<String>> {
+        let messages: Vec<String> = sqlx::query_scalar("pragma integrity_check")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(messages
+            .into_iter()
+            .filter(|message| message != "ok")
+            .collect())
+    }
+
+    /// Calls `f` with the file of each photo, in the columns of all the versions of the schema.
+    /// The rows are streamed.
+    pub async fn for_each_photo_file<F>(&self, mut f: F) -> StoreResult<()>
+    where
+        F: FnMut(PhotoFile),
+    {
+        let mut files = sqlx::query_as::<_, PhotoFile>(
+            "select directory, filename, partial_sha256_hash from photos",
+        )
+        .fetch(&self.pool);
+        while let Some(file) = files.try_next().await? {
+            f(file);
+        }
+        Ok(())
+    }
+
+    /// What the web app added to the database, if it shares it.
+    pub fn web_app(&self) -> Option<&WebAppSchema> {
+        self.web_app.as_ref()
+    }
+
+    /// Records the start of an import in the table of the web app, if it shares the database: the
+    /// photos inserted next are linked to it, to show in the imports of the web app.
+    pub async fn start_import(&mut self) -> StoreResult<Option<i64>> {
+        if !self
+            .web_app
+            .as_ref()
+            .is_some_and(|web_app| web_app.has_imports)
+        {
+            return Ok(None);
+        }
+
+        // the dates are written like Ecto does:
+        let id = sqlx::query(
+            "insert into imports (started_at) values (strftime('%Y-%m-%dT%H:%M:%S', 'now'))",
+        )
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        self.import_id = Some(id);
+        Ok(Some(id))
+    }
+
+    // Compares the migrations applied to the database to the ones of this version, and detects
+    // the tables of the web app.
+    async fn check_schema(&mut self) -> StoreResult<()> {
+        let tables: Vec<String> =
+            sqlx::query_scalar("select name from sqlite_master where type = 'table'")
+                .fetch_all(&self.pool)
+                .await?;
+        if !tables.iter().any(|table| table == "_sqlx_migrations") {
+            return Err(StoreError::SchemaMismatch(
+                "the database was never migrated, run `photor migrate`".to_string(),
+            ));
+        }
+
+        let known: Vec<i64> = MIGRATOR
+            .iter()
+            .filter(|migration| !migration.migration_type.is_down_migration())
+            .map(|migration| migration.version)
+            .collect();
+        let applied: Vec<i64> = sqlx::query_scalar(
+            "select version from _sqlx_migrations where success order by version",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let check = VersionCheck::new(&known, &applied);
+        if let Some(version) = check.unknown.last() {
+            return Err(StoreError::SchemaMismatch(format!(
+                "the database was migrated by a newer version of photor (up to {}), upgrade photor",
+                version
+            )));
+        }
+        if let Some(problem) = self
+            .migrations()
+            .await?
+            .iter()
+            .find_map(|migration| migration.problem())
+        {
+            return Err(StoreError::SchemaMismatch(format!(
+                "{}, see `photor migrate status`",
+                problem
+            )));
+        }
+        // the commands would fail later, on the missing tables and columns:
+        if let Some(version) = check.pending.first() {
+            return Err(StoreError::SchemaMismatch(format!(
+                "{} migration(s) not applied to the database (from {}), run `photor migrate`",
+                check.pending.len(),
+                version
+            )));
+        }
+
+        // the web app keeps its migrations in the `schema_migrations` table of Ecto:
+        if tables.iter().any(|table| table == "schema_migrations") {
+            let applied: Vec<i64> = sqlx::query_scalar("select version from schema_migrations")
+                .fetch_all(&self.pool)
+                .await?;
+            let web_app = WebAppSchema {
+                unknown_migrations: VersionCheck::new(schema::KNOWN_WEB_APP_MIGRATIONS, &applied)
+                    .unknown,
+                has_imports: tables.iter().any(|table| table == "imports"),
+                has_thumbnails: tables.iter().any(|table| table == "thumbnails"),
+            };
+            if !web_app.unknown_migrations.is_empty() {
+                warn!(
+                    "The database was migrated by a newer version of the web app (up to {}), \
+                     photor may not be compatible with it",
+                    web_app.unknown_migrations.last().unwrap_or(&0)
+                );
+            }
+            self.web_app = Some(web_app);
+        }
+
+        Ok(())
+    }
+
+    /// Starts a transaction, for the functions of this module taking a connection.
+    pub async fn begin(&self) -> StoreResult<sqlx::Transaction<'static, Sqlite>> {
+        Ok(self.pool.begin().await?)
+    }
+
+    pub async fn acquire(&self) -> StoreResult<sqlx::pool::PoolConnection<Sqlite>> {
+        Ok(self.pool.acquire().await?)
+    }
+
+    /// Inserts a photo with its tags, video metadata and raw metadata, in one transaction.
+    /// Returns its id.
+    pub async fn insert_photo(
+        &self,
+        photo: &NewPhoto,
+        video: Option<&NewVideo>,
+        raw_metadata: &[u8],
+    ) -> StoreResult<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let gear = Gear::normalize(
+            photo.make.as_deref(),
+            photo.model.as_deref(),
+            photo.lens_model.as_deref(),
+            photo.lens_info.as_deref(),
+        );
+        let gear = apply_gear_aliases(&mut tx, gear).await?;
+
+        let id = sqlx::query!(
+            r#"
+            insert into photos (
+                create_date,
+                create_day,
+                inserted_at,
+                filename,
+                directory,
+                partial_sha256_hash,
+                file_size_bytes,
+                image_height,
+                image_width,
+                mime_type,
+                iso,
+                aperture,
+                shutter_speed,
+                focal_length,
+                make,
+                model,
+                lens_info,
+                lens_make,
+                lens_model,
+                latitude,
+                longitude,
+                altitude,
+                gps_direction,
+                location_source,
+                country_code,
+                country,
+                region,
+                city,
+                caption,
+                exposure_seconds,
+                f_number,
+                focal_length_mm,
+                focal_length_35mm,
+                normalized_make,
+                normalized_model,
+                normalized_lens,
+                camera_serial,
+                lens_serial,
+                shutter_count,
+                orientation,
+                display_width,
+                display_height,
+                rating,
+                color_label
+            )
+            values (?1, date(?1), datetime('now'), ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38, ?39, ?40, ?41, ?42)
+            "#,
+            photo.create_date,
+            photo.filename,
+            photo.directory,
+            photo.partial_sha256_hash,
+            photo.file_size_bytes,
+            photo.image_height,
+            photo.image_width,
+            photo.mime_type,
+            photo.iso,
+            photo.aperture,
+            photo.shutter_speed,
+            photo.focal_length,
+            photo.make,
+            photo.model,
+            photo.lens_info,
+            photo.lens_make,
+            photo.lens_model,
+            photo.latitude,
+            photo.longitude,
+            photo.altitude,
+            photo.gps_direction,
+            photo.location_source,
+            photo.place.country_code,
+            photo.place.country,
+            photo.place.region,
+            photo.place.city,
+            photo.caption,
+            photo.exposure_seconds,
+            photo.f_number,
+            photo.focal_length_mm,
+            photo.focal_length_35mm,
+            gear.make,
+            gear.model,
+            gear.lens,
+            photo.camera_serial,
+            photo.lens_serial,
+            photo.shutter_count,
+            photo.orientation,
+            photo.display_width,
+            photo.display_height,
+            photo.rating,
+            photo.color_label
+        )
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        // the column only exists in the databases shared with the web app:
+        if let Some(import_id) = self.import_id {
+            sqlx::query("update photos set import_id = ?1 where id = ?2")
+                .bind(import_id)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        set_photo_tags(&mut tx, id, &photo.tags).await?;
+        if let Some(video) = video {
+            save_video(&mut *tx, id, video).await?;
+        }
+        save_metadata(&mut *tx, id, raw_metadata).await?;
+
+        tx.commit().await?;
+        Ok(id)
+    }
+
+    pub async fn photo_tags(&self, photo_id: i64) -> StoreResult<Vec<String>> {
+        let tags = sqlx::query_scalar!(
+            r#"
+            select tag from photo_tags where photo_id = ?1 order by rowid
+            "#,
+            photo_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tags)
+    }
+
+    pub async fn video_by_photo_id(&self, photo_id: i64) -> StoreResult<Option<Video>> {
+        let video = sqlx::query_as!(
+            Video,
+            r#"
+            select * from videos where photo_id = ?1
+            "#,
+            photo_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(video)
+    }
+
+    pub async fn metadata_by_photo_id(&self, photo_id: i64) -> StoreResult<Option<Vec<u8>>> {
+        let data = sqlx::query_scalar!(
+            r#"
+            select data from photo_metadata where photo_id = ?1
+            "#,
+            photo_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(data)
+    }
+
+    /// Counts the photos by normalized make, model or lens, with the raw values found for each.
+    pub async fn count_photos_by_gear(&self, kind: GearKind) -> StoreResult<Vec<GearCount>> {
+        // the column names aren't user input:
+        let (column, raw) = match kind {
+            GearKind::Make => ("normalized_make", "make"),
+            GearKind::Model => ("normalized_model", "model"),
+            GearKind::Lens => ("normalized_lens", "coalesce(lens_model, lens_info)"),
+        };
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT name, sum(photos) AS count, group_concat(raw, char(10)) AS raw_values FROM (SELECT ",
+        );
+        query
+            .push(column)
+            .push(" AS name, ")
+            .push(raw)
+            .push(" AS raw, count(*) AS photos FROM photos GROUP BY 1, 2)")
+            .push(" GROUP BY 1 ORDER BY 2 DESC, 1");
+
+        let counts = query
+            .build_query_as::<GearCount>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(counts)
+    }
+
+    /// The usage of each camera body (the bodies of a same model being told apart by their serial
+    /// number, when known).
+    pub async fn body_usages(&self) -> StoreResult<Vec<BodyUsage>> {
+        let usages = sqlx::query_as::<_, BodyUsage>(
+            r#"
+            with bodies as (
+                select
+                    normalized_make as make,
+                    normalized_model as model,
+                    camera_serial as serial,
+                    count(*) as photos,
+                    min(create_date) as first_shot,
+                    max(create_date) as last_shot
+                from photos
+                where normalized_model is not null
+                group by 1, 2, 3
+            ),
+            counts as (
+                select
+                    normalized_make as make,
+                    normalized_model as model,
+                    camera_serial as serial,
+                    shutter_count,
+                    create_date,
+                    row_number() over (
+                        partition by normalized_make, normalized_model, camera_serial
+                        order by shutter_count desc, create_date desc
+                    ) as rank
+                from photos
+                where normalized_model is not null and shutter_count is not null
+            )
+            select
+                bodies.*,
+                counts.shutter_count,
+                counts.create_date as shutter_count_date,
+                counts.shutter_count + (
+                    select count(*) from photos
+                    where normalized_make is bodies.make
+                    and normalized_model is bodies.model
+                    and camera_serial is bodies.serial
+                    and create_date > counts.create_date
+                ) as estimated_shutter_count
+            from bodies
+            left join counts on counts.rank = 1
+                and counts.make is bodies.make
+                and counts.model is bodies.model
+                and counts.serial is bodies.serial
+            order by bodies.make, bodies.model, bodies.serial
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(usages)
+    }
+
+    /// The number of photos taken by each camera body, per year.
+    pub async fn body_usages_by_year(&self) -> StoreResult<Vec<YearUsage>> {
+        let usages = sqlx::query_as!(
+            YearUsage,
+            r#"
+            select
+                normalized_make as make,
+                normalized_model as model,
+                camera_serial as serial,
+                substr(create_date, 1, 4) as "year!: String",
+                count(*) as "photos!: i64"
+            from photos
+            where normalized_model is not null
+            group by 1, 2, 3, 4
+            order by 1, 2, 3, 4
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(usages)
+    }
+
+    /// The photo with the given partial hash, `StoreError::NotFound` if it isn't imported yet.
+    pub async fn photo_by_partial_hash(&self, hash: &str) -> StoreResult<Photo> {
+        // the photos are fetched with the runtime queries, for their dates to be decoded by
+        // `FromRow` (the macros only see text columns):
+        let photo = sqlx::query_as::<_, Photo>(
+            r#"
+            select * from photos where partial_sha256_hash = ?1
+            "#,
+        )
+        .bind(hash)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(photo)
+    }
+
+    /// The photo with the given id, `StoreError::NotFound` if there's none.
+    pub async fn photo_by_id(&self, id: i64) -> StoreResult<Photo> {
+        let photo = sqlx::query_as::<_, Photo>("select * from photos where id = ?1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(photo)
+    }
+
+    /// The photo of the file at the given path of the repository, `StoreError::NotFound` if
+    /// there's none.
+    pub async fn photo_by_path(&self, directory: &str, filename: &str) -> StoreResult<Photo> {
+        let photo = sqlx::query_as::<_, Photo>(
+            "select * from photos where directory = ?1 and filename = ?2",
+        )
+        .bind(directory)
+        .bind(filename)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(photo)
+    }
+
+    /// The import of the web app the photo was imported by, if the database has imports.
+    pub async fn photo_import(&self, photo_id: i64) -> StoreResult<Option<PhotoImport>> {
+        if !self
+            .web_app
+            .as_ref()
+            .is_some_and(|web_app| web_app.has_imports)
+        {
+            return Ok(None);
+        }
+
+        let import = sqlx::query_as::<_, PhotoImport>(
+            r#"
+            select i.id, i.started_at
+            from imports i
+            join photos p on p.import_id = i.id
+            where p.id = ?1
+            "#,
+        )
+        .bind(photo_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(import)
+    }
+
+    /// The archives holding the photo, in the order it was added to them.
+    pub async fn photo_archives(&self, photo_id: i64) -> StoreResult<Vec<PhotoArchive>> {
+        let archives = sqlx::query_as::<_, PhotoArchive>(
+            r#"
+            select i.archive_id, a.media_type, i.inserted_at
+            from archives_items i
+            join archives a on a.id = i.archive_id
+            where i.item_id = ?1
+            order by i.id
+            "#,
+        )
+        .bind(photo_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(archives)
+    }
+
+    /// The thumbnails made of the photo by the web app, if the database has thumbnails.
+    pub async fn photo_thumbnails(&self, photo_id: i64) -> StoreResult<Vec<Thumbnail>> {
+        if !self
+            .web_app
+            .as_ref()
+            .is_some_and(|web_app| web_app.has_thumbnails)
+        {
+            return Ok(Vec::new());
+        }
+
+        let thumbnails = sqlx::query_as::<_, Thumbnail>(
+            "select size_name, width, height from thumbnails where photo_id = ?1 order by width",
+        )
+        .bind(photo_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(thumbnails)
+    }
+
+    /// The photos taken between the two dates (in the camera time) without location. With
+    /// `overwrite`, the ones whose location was set by a previous geotag are also returned.
+    pub async fn photos_to_geotag(
+        &self,
+        from: NaiveDateTime,
+        to: NaiveDateTime,
+        overwrite: bool,
+    ) -> StoreResult<Vec<Photo>> {
+        let photos = sqlx::query_as::<_, Photo>(
+            r#"
+            select * from photos
+            where datetime(create_date) between ?1 and ?2
+            and (latitude is null or (?3 and location_source = 'gpx'))
+            order by create_date, id
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .bind(overwrite)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(photos)
+    }
+
+    /// The next photos having a location but no place names yet, by id, after `after_id`.
+    pub async fn photos_without_place(&self, after_id: i64, limit: u32) -> StoreResult<Vec<Photo>> {
+        let photos = sqlx::query_as::<_, Photo>(
+            r#"
+            select * from photos
+            where id > ?1
+            and latitude is not null and longitude is not null and country_code is null
+            order by id
+            limit ?2
+            "#,
+        )
+        .bind(after_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(photos)
+    }
+
+    /// Counts the photos by place, grouped by country, region and city (the finer levels being
+    /// dropped with `level` 1 or 2). The photos without place are counted together.
+    pub async fn count_photos_by_place(&self, level: u8) -> StoreResult<Vec<PlaceCount>> {
+        let counts = sqlx::query_as!(
+            PlaceCount,
+            r#"
+            select
+                country,
+                case when ?1 >= 2 then region end as region,
+                case when ?1 >= 3 then city end as city,
+                count(*) as "count!: i64"
+            from photos
+            group by 1, 2, 3
+            order by 4 desc, 1, 2, 3
+            "#,
+            level
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    pub async fn stats(&self) -> StoreResult<Stats> {
+        let stats = sqlx::query_as!(
+            Stats,
+            r#"
+            select
+                (select count(*) from photos) as "photos!: i64",
+                (select count(*) from videos) as "videos!: i64",
+                (select coalesce(sum(file_size_bytes), 0) from photos) as "total_size_bytes!: i64",
+                (select coalesce(sum(duration_seconds), 0.0) from videos) as "total_video_seconds!: f64"
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Counts the videos by codec, the most used first.
+    pub async fn count_videos_by_codec(&self) -> StoreResult<Vec<CodecCount>> {
+        let counts = sqlx::query_as!(
+            CodecCount,
+            r#"
+            select
+                video_codec,
+                count(*) as "count!: i64",
+                coalesce(sum(duration_seconds), 0.0) as "duration_seconds!: f64"
+            from videos
+            group by 1
+            order by 2 desc, 1
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
+    /// The next photos to refresh, by id, after `after_id`. The `missing` SQL conditions select the
+    /// photos missing some data (any of them).
+    pub async fn photos_to_refresh(
+        &self,
+        after_id: i64,
+        since: Option<&str>,
+        missing: &[&str],
+        limit: u32,
+    ) -> StoreResult<Vec<Photo>> {
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM photos WHERE id > ");
+        query.push_bind(after_id);
+
+        if let Some(since) = since {
+            query
+                .push(" AND datetime(create_date) >= ")
+                .push_bind(since.to_string());
+        }
+
+        if !missing.is_empty() {
+            query.push(" AND (");
+            for (i, condition) in missing.iter().enumerate() {
+                if i > 0 {
+                    query.push(" OR ");
+                }
+                query.push(condition);
+            }
+            query.push(")");
+        }
+
+        query.push(" ORDER BY id LIMIT ").push_bind(limit);
+
+        let photos = query
+            .build_query_as::<Photo>()
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(photos)
+    }
+
+    /// The last photo processed by an interrupted metadata refresh having the same options.
+    pub async fn refresh_progress(&self, options: &str) -> StoreResult<Option<i64>> {
+        let last_photo_id = sqlx::query_scalar!(
+            r#"
+            select last_photo_id from metadata_refreshes where options = ?1
+            "#,
+            options
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(last_photo_id)
+    }
+
+    pub async fn delete_refresh_progress(&self, options: &str) -> StoreResult<()> {
+        sqlx::query!(
+            r#"
+            delete from metadata_refreshes where options = ?1
+            "#,
+            options
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Calls `f` on the photos matching the filters, one at a time as they're read: the next
+    /// ones are read once `f` is done with the previous one, whatever their number.
+    pub async fn for_each_photo<F, E>(&self, filters: &PhotoFilters, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(Photo) -> Result<(), E>,
+        E: From<StoreError>,
+    {
+        let mut query = photos_query(filters);
+        let mut photos = query.build_query_as::<Photo>().fetch(&self.pool);
+
+        // the distance to the --near position is checked on the rows, the page is taken after:
+        let mut skipped = 0;
+        let mut taken = 0;
+        while let Some(photo) = photos.try_next().await.map_err(StoreError::from)? {
+            if let Some((point, radius)) = &filters.near {
+                let near = match (photo.latitude, photo.longitude) {
+                    (Some(latitude), Some(longitude)) => {
+                        point.distance_meters(&Point {
+                            latitude,
+                            longitude,
+                        }) <= *radius
+                    }
+                    _ => false,
+                };
+                if !near {
+                    continue;
+                }
+                if skipped < filters.offset.unwrap_or(0) {
+                    skipped += 1;
+                    continue;
+                }
+                if filters.limit.is_some_and(|limit| taken >= limit) {
+                    break;
+                }
+                taken += 1;
+            }
+            f(photo)?;
+        }
+        Ok(())
+    }
+
+    /// The saved queries, by name.
+    pub async fn saved_queries(&self) -> StoreResult<Vec<SavedQuery>> {
+        let queries = sqlx::query_as::<_, SavedQuery>(
+            "select name, query, updated_at from saved_queries order by name",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(queries)
+    }
+
+    /// Saves a query, replacing the one of the same name.
+    pub async fn save_query(&self, name: &str, query: &str) -> StoreResult<()> {
+        sqlx::query(
+            r#"
+            insert into saved_queries (name, query, updated_at)
+            values (?1, ?2, datetime('now'))
+            on conflict (name) do update set query = ?2, updated_at = datetime('now')
+            "#,
+        )
+        .bind(name)
+        .bind(query)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Deletes a saved query. Returns false if there's none of this name.
+    pub async fn delete_query(&self, name: &str) -> StoreResult<bool> {
+        let result = sqlx::query("delete from saved_queries where name = ?1")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Adds the photos matching the selection to an archive, but the ones already in it. Returns
+    /// the number of photos added.
+    pub async fn add_to_archive(&self, archive_id: &str, selection: &Query) -> StoreResult<u64> {
+        let exists: bool =
+            sqlx::query_scalar("select exists (select 1 from archives where id = ?1)")
+                .bind(archive_id)
+                .fetch_one(&self.pool)
+                .await?;
+        if !exists {
+            return Err(StoreError::NotFound);
+        }
+
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "insert into archives_items (archive_id, item_id, inserted_at) select ",
+        );
+        query
+            .push_bind(archive_id)
+            .push(", id, datetime('now') from photos where ");
+        push_query(&mut query, selection);
+        query
+            .push(" and id not in (select item_id from archives_items where archive_id = ")
+            .push_bind(archive_id)
+            .push(") order by create_date, id");
+
+        Ok(query.build().execute(&self.pool).await?.rows_affected())
+    }
+
+    /// The id of the last import of the web app, if it shares the database.
+    pub async fn last_import_id(&self) -> StoreResult<Option<i64>> {
+        let id = sqlx::query_scalar("select max(id) from imports")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id)
+    }
+
+    /// Calls `f` on the records dumped by `photor catalog export`: the photos matching the
+    /// selection, then the archives holding them, then their items, with their tags. All the
+    /// columns are dumped, the ones added by newer migrations included. The photos and items are
+    /// read as they're written.
+    pub async fn for_each_catalog_record<F, E>(&self, selection: &Query, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(RecordType, Record) -> Result<(), E>,
+        E: From<StoreError>,
+    {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            r#"
+            select p.*, (
+                select json_group_array(tag)
+                from (select tag from photo_tags where photo_id = p.id order by tag)
+            ) as tags
+            from photos p
+            where "#,
+        );
+        push_query(&mut query, selection);
+        query.push(" order by id");
+        let mut rows = query.build().fetch(&self.pool);
+        while let Some(row) = rows.try_next().await.map_err(StoreError::from)? {
+            let mut record = row_record(&row)?;
+            let tags = match record.remove("tags") {
+                Some(JsonValue::String(tags)) => serde_json::from_str(&tags).unwrap_or_default(),
+                _ => Vec::new(),
+            };
+            record.insert("tags".to_string(), JsonValue::Array(tags));
+            f(RecordType::Photo, record)?;
+        }
+        drop(rows);
+
+        let mut conn = self.pool.acquire().await.map_err(StoreError::from)?;
+        let mut archive_tags: HashMap<String, Vec<JsonValue>> = HashMap::new();
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("select archive_id, tag from archive_tags order by archive_id, tag")
+                .fetch_all(&mut *conn)
+                .await
+                .map_err(StoreError::from)?;
+        for (archive_id, tag) in rows {
+            archive_tags.entry(archive_id).or_default().push(tag.into());
+        }
+        let mut holders: HashMap<String, Vec<JsonValue>> = HashMap::new();
+        for row in sqlx::query(
+            "select archive_id, name, given_from, back_on from archives_holders order by id",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(StoreError::from)?
+        {
+            let mut record = row_record(&row)?;
+            if let Some(JsonValue::String(archive_id)) = record.remove("archive_id") {
+                holders
+                    .entry(archive_id)
+                    .or_default()
+                    .push(JsonValue::Object(record));
+            }
+        }
+
+        // only the archives holding some of the selected photos are dumped, unless all of them
+        // are:
+        let mut query = QueryBuilder::<Sqlite>::new("select * from archives");
+        if *selection != Query::All {
+            query.push(
+                " where id in (select archive_id from archives_items where item_id in (select id from photos where ",
+            );
+            push_query(&mut query, selection);
+            query.push("))");
+        }
+        query.push(" order by id");
+        for row in query
+            .build()
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(StoreError::from)?
+        {
+            let mut record = row_record(&row)?;
+            let id = record
+                .get("id")
+                .and_then(JsonValue::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let tags = archive_tags.remove(&id).unwrap_or_default();
+            record.insert("tags".to_string(), JsonValue::Array(tags));
+            let holders = holders.remove(&id).unwrap_or_default();
+            record.insert("holders".to_string(), JsonValue::Array(holders));
+            f(RecordType::Archive, record)?;
+        }
+
+        // the items refer to the photos by hash, their ids not being kept by a merge:
+        let mut query = QueryBuilder::<Sqlite>::new(
+            r#"
+            select i.archive_id, p.partial_sha256_hash, i.inserted_at
+            from archives_items i
+            join photos p on p.id = i.item_id
+            where i.item_id in (select id from photos where "#,
+        );
+        push_query(&mut query, selection);
+        query.push(") order by i.archive_id, i.id");
+        let mut rows = query.build().fetch(&mut *conn);
+        while let Some(row) = rows.try_next().await.map_err(StoreError::from)? {
+            f(RecordType::ArchiveItem, row_record(&row)?)?;
+        }
+
+        Ok(())
+    }
 }
 
-pub async fn migrate() -> Result<()> {
-    let pool = pool().await?;
-    sqlx::migrate!("./migrations").run(&pool).await?;
+/// Writes a catalog dump to the database, matching the photos by their partial hash: the
+/// missing photos, archives and items are inserted, and the tags are added to the existing ones.
+/// With `rebuild`, the photos and archives of the database are deleted first, and the ids of the
+/// dump are kept.
+pub async fn import_catalog(
+    conn: &mut SqliteConnection,
+    catalog: &Catalog,
+    rebuild: bool,
+) -> StoreResult<CatalogCounts> {
+    let mut counts = CatalogCounts::default();
+
+    if rebuild {
+        // the videos and metadata aren't in the dump, `photor metadata refresh` recreates them:
+        for table in [
+            "archives_items",
+            "archives_holders",
+            "archive_tags",
+            "archives",
+            "photo_tags",
+            "videos",
+            "photo_metadata",
+            "metadata_refreshes",
+            "photos",
+        ] {
+            sqlx::query(&format!("delete from {}", table))
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    // the ids of the photos are only kept when nothing can conflict with them, and the imports
+    // are the ones of the web app sharing the database, not of the dump:
+    let skipped: &[&str] = if rebuild {
+        &["tags", "import_id"]
+    } else {
+        &["id", "tags", "import_id"]
+    };
+    let photo_columns = table_columns(conn, "photos").await?;
+    for photo in &catalog.photos {
+        let hash = record_text(photo, "partial_sha256_hash")
+            .ok_or_else(|| StoreError::Constraint("photo without partial_sha256_hash".into()))?;
+        let photo_id = match photo_id_by_hash(conn, &hash).await? {
+            Some(id) => id,
+            None => {
+                counts.photos += 1;
+                insert_record(conn, "photos", &photo_columns, photo, skipped).await?
+            }
+        };
+
+        for tag in record_list(photo, "tags").iter().filter_map(value_text) {
+            counts.photo_tags +=
+                sqlx::query("insert or ignore into photo_tags (photo_id, tag) values (?1, ?2)")
+                    .bind(photo_id)
+                    .bind(tag)
+                    .execute(&mut *conn)
+                    .await?
+                    .rows_affected();
+        }
+    }
+
+    let archive_columns = table_columns(conn, "archives").await?;
+    let holder_columns = table_columns(conn, "archives_holders").await?;
+    for archive in &catalog.archives {
+        let id = record_text(archive, "id")
+            .ok_or_else(|| StoreError::Constraint("archive without id".into()))?;
+        let exists: bool =
+            sqlx::query_scalar("select exists (select 1 from archives where id = ?1)")
+                .bind(&id)
+                .fetch_one(&mut *conn)
+                .await?;
+        // the holders are those of the dump only for a new archive, they have no key to merge on:
+        if !exists {
+            counts.archives += 1;
+            insert_record(
+                conn,
+                "archives",
+                &archive_columns,
+                archive,
+                &["tags", "holders"],
+            )
+            .await?;
+            for holder in record_list(archive, "holders") {
+                let mut holder = holder.as_object().cloned().unwrap_or_default();
+                holder.insert("archive_id".to_string(), id.clone().into());
+                insert_record(conn, "archives_holders", &holder_columns, &holder, &["id"]).await?;
+            }
+        }
+
+        for tag in record_list(archive, "tags").iter().filter_map(value_text) {
+            counts.archive_tags +=
+                sqlx::query("insert or ignore into archive_tags (archive_id, tag) values (?1, ?2)")
+                    .bind(&id)
+                    .bind(tag)
+                    .execute(&mut *conn)
+                    .await?
+                    .rows_affected();
+        }
+    }
+
+    for item in &catalog.archive_items {
+        let archive_id = record_text(item, "archive_id");
+        let photo_id = match record_text(item, "partial_sha256_hash") {
+            Some(hash) => photo_id_by_hash(conn, &hash).await?,
+            None => None,
+        };
+        let photo_id = match photo_id {
+            Some(photo_id) => photo_id,
+            None => {
+                counts.skipped_items += 1;
+                continue;
+            }
+        };
+
+        let exists: bool = sqlx::query_scalar(
+            "select exists (select 1 from archives_items where archive_id = ?1 and item_id = ?2)",
+        )
+        .bind(&archive_id)
+        .bind(photo_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        if !exists {
+            counts.archive_items += 1;
+            sqlx::query(
+                "insert into archives_items (archive_id, item_id, inserted_at) values (?1, ?2, ?3)",
+            )
+            .bind(&archive_id)
+            .bind(photo_id)
+            .bind(record_text(item, "inserted_at"))
+            .execute(&mut *conn)
+            .await?;
+        }
+    }
+
+    Ok(counts)
+}
+
+// The columns of a table, with their declared types.
+async fn table_columns(
+    conn: &mut SqliteConnection,
+    table: &str,
+) -> StoreResult<Vec<(String, String)>> {
+    let columns = sqlx::query_as("select name, type from pragma_table_info(?1)")
+        .bind(table)
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(columns)
+}
+
+// Inserts the values of the record which are columns of the table (but the `skipped` ones), the
+// nulls getting the default of their column (like the empty values of CSV). Returns the id of the
+// row.
+async fn insert_record(
+    conn: &mut SqliteConnection,
+    table: &str,
+    columns: &[(String, String)],
+    record: &Record,
+    skipped: &[&str],
+) -> StoreResult<i64> {
+    let values: Vec<(&String, ColumnValue)> = columns
+        .iter()
+        .filter(|(name, _)| !skipped.contains(&name.as_str()))
+        .filter_map(|(name, declared_type)| {
+            let value = record.get(name).filter(|value| !value.is_null())?;
+            Some((name, catalog::column_value(value, declared_type)))
+        })
+        .collect();
+
+    let mut query = QueryBuilder::<Sqlite>::new(format!("insert into {} (", table));
+    let mut names = query.separated(", ");
+    for (name, _) in &values {
+        names.push(name.as_str());
+    }
+    query.push(") values (");
+    let mut binds = query.separated(", ");
+    for (_, value) in &values {
+        match value {
+            ColumnValue::Integer(value) => binds.push_bind(*value),
+            ColumnValue::Real(value) => binds.push_bind(*value),
+            ColumnValue::Text(value) => binds.push_bind(value.clone()),
+        };
+    }
+    query.push(")");
+
+    Ok(query.build().execute(&mut *conn).await?.last_insert_rowid())
+}
+
+async fn photo_id_by_hash(conn: &mut SqliteConnection, hash: &str) -> StoreResult<Option<i64>> {
+    let id = sqlx::query_scalar("select id from photos where partial_sha256_hash = ?1")
+        .bind(hash)
+        .fetch_optional(&mut *conn)
+        .await?;
+    Ok(id)
+}
+
+// The value of a column of a row, whatever its type, for a catalog dump. The blobs are written
+// in hexadecimal.
+fn row_record(row: &SqliteRow) -> StoreResult<Record> {
+    let mut record = Record::new();
+    for column in row.columns() {
+        let index = column.ordinal();
+        let raw = row.try_get_raw(index)?;
+        let value = if raw.is_null() {
+            JsonValue::Null
+        } else {
+            match raw.type_info().name() {
+                "INTEGER" => row.try_get_unchecked::<i64, _>(index)?.into(),
+                "REAL" => row.try_get_unchecked::<f64, _>(index)?.into(),
+                "BLOB" => row
+                    .try_get_unchecked::<Vec<u8>, _>(index)?
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<String>()
+                    .into(),
+                _ => row.try_get_unchecked::<String, _>(index)?.into(),
+            }
+        };
+        record.insert(column.name().to_string(), value);
+    }
+    Ok(record)
+}
+
+fn record_text(record: &Record, key: &str) -> Option<String> {
+    record.get(key).and_then(value_text)
+}
+
+// A value of a dump as text: the numbers of a CSV dump are strings, the ones of JSON aren't.
+fn value_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(text) => Some(text.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+fn record_list(record: &Record, key: &str) -> Vec<JsonValue> {
+    record
+        .get(key)
+        .and_then(JsonValue::as_array)
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Replaces the tags of a photo.
+pub async fn set_photo_tags(
+    conn: &mut SqliteConnection,
+    photo_id: i64,
+    tags: &[String],
+) -> StoreResult<()> {
+    sqlx::query!(
+        r#"
+        delete from photo_tags where photo_id = ?1
+        "#,
+        photo_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for tag in tags {
+        sqlx::query!(
+            r#"
+            insert or ignore into photo_tags (photo_id, tag) values (?1, ?2)
+            "#,
+            photo_id,
+            tag
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
     Ok(())
 }
 
-pub async fn insert_photo(pool: &SqlitePool, photo: NewPhoto) -> Result<i64> {
-    let mut conn = pool.acquire().await.unwrap();
+/// Inserts or replaces the video metadata of a photo.
+pub async fn save_video<'e, E>(executor: E, photo_id: i64, video: &NewVideo) -> StoreResult<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query!(
+        r#"
+        insert into videos (
+            photo_id,
+            duration_seconds,
+            video_codec,
+            audio_codec,
+            frame_rate,
+            bitrate,
+            rotation,
+            display_width,
+            display_height
+        )
+        values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        on conflict (photo_id) do update set
+            duration_seconds = excluded.duration_seconds,
+            video_codec = excluded.video_codec,
+            audio_codec = excluded.audio_codec,
+            frame_rate = excluded.frame_rate,
+            bitrate = excluded.bitrate,
+            rotation = excluded.rotation,
+            display_width = excluded.display_width,
+            display_height = excluded.display_height
+        "#,
+        photo_id,
+        video.duration_seconds,
+        video.video_codec,
+        video.audio_codec,
+        video.frame_rate,
+        video.bitrate,
+        video.rotation,
+        video.display_width,
+        video.display_height
+    )
+    .execute(executor)
+    .await?;
 
-    let id = sqlx::query!(
+    Ok(())
+}
+
+/// Stores (or replaces) the complete exiftool output of a photo, compressed by
+/// `metadata::compress`.
+pub async fn save_metadata<'e, E>(executor: E, photo_id: i64, data: &[u8]) -> StoreResult<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    sqlx::query!(
         r#"
-        insert into photos (
-            create_date,
-            inserted_at,
-            filename,
-            directory,
-            partial_sha256_hash,
-            file_size_bytes,
-            image_height,
-            image_width,
-            mime_type,
-            iso,
-            aperture,
-            shutter_speed,
-            focal_length,
-            make,
-            model,
-            lens_info,
-            lens_make,
-            lens_model
-        )
-        values (?1, datetime('now'), ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+        insert into photo_metadata (photo_id, data)
+        values (?1, ?2)
+        on conflict (photo_id) do update set data = excluded.data
         "#,
-        photo.create_date,
-        photo.filename,
-        photo.directory,
-        photo.partial_sha256_hash,
-        photo.file_size_bytes,
-        photo.image_height,
-        photo.image_width,
-        photo.mime_type,
-        photo.iso,
-        photo.aperture,
-        photo.shutter_speed,
-        photo.focal_length,
-        photo.make,
-        photo.model,
-        photo.lens_info,
-        photo.lens_make,
-        photo.lens_model
+        photo_id,
+        data
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Replaces the normalized names having an alias by the alias name.
+pub async fn apply_gear_aliases(conn: &mut SqliteConnection, mut gear: Gear) -> StoreResult<Gear> {
+    for kind in [GearKind::Make, GearKind::Model, GearKind::Lens] {
+        let Some(value) = gear.get(kind) else {
+            continue;
+        };
+        let kind_name = kind.as_str();
+        let name = sqlx::query_scalar!(
+            r#"
+            select name from gear_aliases where kind = ?1 and value = ?2
+            "#,
+            kind_name,
+            value
+        )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some(name) = name {
+            gear.set(kind, name);
+        }
+    }
+
+    Ok(gear)
+}
+
+/// Gives a name to a normalized make, model or lens. The aliases to `value` are redirected to
+/// the new name, so that they're never chained.
+pub async fn set_gear_alias(
+    conn: &mut SqliteConnection,
+    kind: GearKind,
+    value: &str,
+    name: &str,
+) -> StoreResult<()> {
+    let kind = kind.as_str();
+
+    sqlx::query!(
+        r#"
+        update gear_aliases set name = ?3 where kind = ?1 and name = ?2
+        "#,
+        kind,
+        value,
+        name
     )
     .execute(&mut *conn)
-    .await
-    .unwrap()
-    .last_insert_rowid();
+    .await?;
 
-    Ok(id)
+    sqlx::query!(
+        r#"
+        insert into gear_aliases (kind, value, name)
+        values (?1, ?2, ?3)
+        on conflict (kind, value) do update set name = excluded.name
+        "#,
+        kind,
+        value,
+        name
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // an alias of a name to itself is useless:
+    sqlx::query!(
+        r#"
+        delete from gear_aliases where kind = ?1 and value = name
+        "#,
+        kind
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
 }
 
-pub async fn photo_lookup_by_partial_hash(pool: &SqlitePool, hash: &str) -> Option<Photo> {
-    sqlx::query_as!(
-        Photo,
+/// Normalizes again the make, model and lens of all the photos, applying the current aliases.
+/// Returns the number of photos whose names changed.
+pub async fn normalize_all_gear(conn: &mut SqliteConnection) -> StoreResult<u64> {
+    let combinations = sqlx::query!(
         r#"
-        select * from photos where partial_sha256_hash = ?1
+        select distinct
+            make, model, lens_model, lens_info,
+            normalized_make, normalized_model, normalized_lens
+        from photos
+        "#
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut changed = 0;
+    for row in combinations {
+        let gear = Gear::normalize(
+            row.make.as_deref(),
+            row.model.as_deref(),
+            row.lens_model.as_deref(),
+            row.lens_info.as_deref(),
+        );
+        let gear = apply_gear_aliases(conn, gear).await?;
+        let current = Gear {
+            make: row.normalized_make,
+            model: row.normalized_model,
+            lens: row.normalized_lens,
+        };
+        if gear == current {
+            continue;
+        }
+
+        changed += sqlx::query!(
+            r#"
+            update photos
+            set normalized_make = ?5, normalized_model = ?6, normalized_lens = ?7
+            where make is ?1 and model is ?2 and lens_model is ?3 and lens_info is ?4
+            "#,
+            row.make,
+            row.model,
+            row.lens_model,
+            row.lens_info,
+            gear.make,
+            gear.model,
+            gear.lens
+        )
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(changed)
+}
+
+pub async fn set_photo_location(
+    conn: &mut SqliteConnection,
+    id: i64,
+    latitude: f64,
+    longitude: f64,
+    altitude: Option<f64>,
+    source: &str,
+) -> StoreResult<()> {
+    sqlx::query!(
+        r#"
+        update photos
+        set latitude = ?2, longitude = ?3, altitude = ?4, location_source = ?5
+        where id = ?1
         "#,
-        hash
+        id,
+        latitude,
+        longitude,
+        altitude,
+        source
     )
-    .fetch_optional(pool)
-    .await
-    .unwrap()
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
 }
 
-pub async fn list_photos(pool: &SqlitePool) -> anyhow::Result<Vec<Photo>> {
-    let stream = sqlx::query_as::<_, Photo>(
+pub async fn set_photo_place(
+    conn: &mut SqliteConnection,
+    id: i64,
+    place: &Place,
+) -> StoreResult<()> {
+    sqlx::query!(
         r#"
-SELECT *
-FROM photos
-ORDER BY create_date, id
+        update photos
+        set country_code = ?2, country = ?3, region = ?4, city = ?5
+        where id = ?1
         "#,
+        id,
+        place.country_code,
+        place.country,
+        place.region,
+        place.city
     )
-    .fetch_all(pool)
+    .execute(conn)
     .await?;
 
-    Ok(stream)
+    Ok(())
+}
+
+/// Sets the given columns of a photo. The column names aren't user input.
+pub async fn update_photo_columns(
+    conn: &mut SqliteConnection,
+    id: i64,
+    columns: &[(&str, ColumnValue)],
+) -> StoreResult<()> {
+    if columns.is_empty() {
+        return Ok(());
+    }
+
+    let mut query = QueryBuilder::<Sqlite>::new("UPDATE photos SET ");
+    for (i, (column, value)) in columns.iter().enumerate() {
+        if i > 0 {
+            query.push(", ");
+        }
+        query.push(column).push(" = ");
+        match value {
+            ColumnValue::Integer(value) => query.push_bind(*value),
+            ColumnValue::Real(value) => query.push_bind(*value),
+            ColumnValue::Text(value) => query.push_bind(value.clone()),
+        };
+    }
+    query.push(" WHERE id = ").push_bind(id);
+
+    query.build().execute(&mut *conn).await?;
+    Ok(())
+}
+
+pub async fn save_refresh_progress(
+    conn: &mut SqliteConnection,
+    options: &str,
+    last_photo_id: i64,
+) -> StoreResult<()> {
+    sqlx::query!(
+        r#"
+        insert into metadata_refreshes (options, last_photo_id, updated_at)
+        values (?1, ?2, datetime('now'))
+        on conflict (options) do update set
+            last_photo_id = excluded.last_photo_id,
+            updated_at = excluded.updated_at
+        "#,
+        options,
+        last_photo_id
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// The criteria used to select the photos to list. A photo has to match all of them.
+#[derive(Default)]
+pub struct PhotoFilters {
+    /// A position, and the maximum distance to it in meters.
+    pub near: Option<(Point, f64)>,
+    pub bbox: Option<BoundingBox>,
+    /// A city, region or country name, or a country code.
+    pub place: Option<String>,
+    /// Bounds of the duration of videos, in seconds. Photos don't match them.
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    /// Conditions on the raw metadata (the exiftool output).
+    pub meta: Vec<MetaCondition>,
+    /// Exposure times in seconds, both excluded.
+    pub exposure_longer_than: Option<f64>,
+    pub exposure_shorter_than: Option<f64>,
+    pub f_number: Option<Range>,
+    /// In millimeters.
+    pub focal_length: Option<Range>,
+    pub focal_length_35mm: Option<Range>,
+    /// Tags the photos must all have (ignoring the case).
+    pub tags: Vec<String>,
+    pub min_rating: Option<i64>,
+    /// Days of the `create_day` column, both included.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    /// Parts of the gear names, raw or normalized, ignoring the case.
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+    /// A MIME type, or a pattern like `video/*`.
+    pub mime_type: Option<String>,
+    pub iso: Option<Range>,
+    /// In bytes.
+    pub size: Option<Range>,
+    /// The import of the web app the photos were added by.
+    pub import_id: Option<i64>,
+    pub archive: ArchiveFilter,
+    pub sort: PhotoSort,
+    pub reverse: bool,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    /// A query of `photor list` (its saved queries expanded).
+    pub query: Option<Query>,
+}
+
+/// Whether the photos listed must be in an archive.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ArchiveFilter {
+    #[default]
+    Any,
+    NotArchived,
+    InArchive(String),
+}
+
+/// The orders of the photos listed, the id breaking the ties.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum PhotoSort {
+    /// The date the photo was taken
+    #[default]
+    Date,
+    /// The date the photo was imported
+    Inserted,
+    /// The size of the file
+    Size,
+    /// The path of the file in the repository
+    Path,
+    /// The order of the imports
+    Id,
+}
+
+impl PhotoSort {
+    fn columns(&self) -> &'static [&'static str] {
+        match self {
+            PhotoSort::Date => &["create_date", "id"],
+            PhotoSort::Inserted => &["inserted_at", "id"],
+            PhotoSort::Size => &["file_size_bytes", "id"],
+            PhotoSort::Path => &["directory", "filename"],
+            PhotoSort::Id => &["id"],
+        }
+    }
+}
+
+fn push_bbox(query: &mut QueryBuilder<Sqlite>, bbox: &BoundingBox) {
+    query
+        .push(" AND latitude BETWEEN ")
+        .push_bind(bbox.min_latitude)
+        .push(" AND ")
+        .push_bind(bbox.max_latitude)
+        .push(" AND longitude BETWEEN ")
+        .push_bind(bbox.min_longitude)
+        .push(" AND ")
+        .push_bind(bbox.max_longitude);
+}
+
+fn push_range(query: &mut QueryBuilder<Sqlite>, column: &str, range: &Range) {
+    if let Some(min) = range.min {
+        query.push(" AND ").push(column).push(" >= ").push_bind(min);
+    }
+    if let Some(max) = range.max {
+        query.push(" AND ").push(column).push(" <= ").push_bind(max);
+    }
+}
+
+// The query of the photos matching the filters, in their order.
+fn photos_query(filters: &PhotoFilters) -> QueryBuilder<'_, Sqlite> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM photos WHERE 1 = 1");
+
+    // the location index is used to select the photos in the bounding box of the radius, the
+    // exact distance being checked afterwards:
+    if let Some((point, radius)) = &filters.near {
+        push_bbox(&mut query, &point.bounding_box(*radius));
+    }
+    if let Some(bbox) = &filters.bbox {
+        push_bbox(&mut query, bbox);
+    }
+
+    if let Some(place) = &filters.place {
+        query
+            .push(" AND (city = ")
+            .push_bind(place)
+            .push(" COLLATE NOCASE OR region = ")
+            .push_bind(place)
+            .push(" COLLATE NOCASE OR country = ")
+            .push_bind(place)
+            .push(" COLLATE NOCASE OR country_code = ")
+            .push_bind(place)
+            .push(" COLLATE NOCASE)");
+    }
+
+    if filters.min_duration.is_some() || filters.max_duration.is_some() {
+        query.push(" AND id IN (SELECT photo_id FROM videos WHERE 1 = 1");
+        if let Some(min_duration) = filters.min_duration {
+            query
+                .push(" AND duration_seconds >= ")
+                .push_bind(min_duration);
+        }
+        if let Some(max_duration) = filters.max_duration {
+            query
+                .push(" AND duration_seconds <= ")
+                .push_bind(max_duration);
+        }
+        query.push(")");
+    }
+
+    if let Some(seconds) = filters.exposure_longer_than {
+        query.push(" AND exposure_seconds > ").push_bind(seconds);
+    }
+    if let Some(seconds) = filters.exposure_shorter_than {
+        query.push(" AND exposure_seconds < ").push_bind(seconds);
+    }
+    if let Some(range) = &filters.f_number {
+        push_range(&mut query, "f_number", range);
+    }
+    if let Some(range) = &filters.focal_length {
+        push_range(&mut query, "focal_length_mm", range);
+    }
+    if let Some(range) = &filters.focal_length_35mm {
+        push_range(&mut query, "focal_length_35mm", range);
+    }
+
+    for tag in &filters.tags {
+        query
+            .push(" AND id IN (SELECT photo_id FROM photo_tags WHERE tag = ")
+            .push_bind(tag.clone())
+            .push(" COLLATE NOCASE)");
+    }
+    if let Some(rating) = filters.min_rating {
+        query.push(" AND rating >= ").push_bind(rating);
+    }
+
+    for condition in &filters.meta {
+        push_meta_condition(&mut query, condition);
+    }
+
+    if let Some(from) = filters.from {
+        query.push(" AND create_day >= ").push_bind(from);
+    }
+    if let Some(to) = filters.to {
+        query.push(" AND create_day <= ").push_bind(to);
+    }
+
+    let gear = [
+        (&filters.make, ["make", "normalized_make"]),
+        (&filters.model, ["model", "normalized_model"]),
+        (&filters.lens, ["lens_model", "normalized_lens"]),
+    ];
+    for (name, [raw, normalized]) in gear {
+        if let Some(name) = name {
+            let name = name.to_lowercase();
+            query
+                .push(format!(" AND (instr(lower({}), ", raw))
+                .push_bind(name.clone())
+                .push(format!(") > 0 OR instr(lower({}), ", normalized))
+                .push_bind(name)
+                .push(") > 0)");
+        }
+    }
+
+    if let Some(mime_type) = &filters.mime_type {
+        match mime_type.strip_suffix("/*") {
+            Some(kind) => query
+                .push(" AND mime_type LIKE ")
+                .push_bind(format!("{}/%", kind)),
+            None => query.push(" AND mime_type = ").push_bind(mime_type.clone()),
+        };
+    }
+    if let Some(range) = &filters.iso {
+        push_range(&mut query, "iso", range);
+    }
+    if let Some(range) = &filters.size {
+        push_range(&mut query, "file_size_bytes", range);
+    }
+
+    if let Some(import_id) = filters.import_id {
+        query.push(" AND import_id = ").push_bind(import_id);
+    }
+    if let Some(selection) = &filters.query {
+        query.push(" AND ");
+        push_query(&mut query, selection);
+    }
+    match &filters.archive {
+        ArchiveFilter::Any => (),
+        ArchiveFilter::NotArchived => {
+            query.push(" AND id NOT IN (SELECT item_id FROM archives_items)");
+        }
+        ArchiveFilter::InArchive(archive_id) => {
+            query
+                .push(" AND id IN (SELECT item_id FROM archives_items WHERE archive_id = ")
+                .push_bind(archive_id.clone())
+                .push(")");
+        }
+    }
+
+    let direction = if filters.reverse { " DESC" } else { "" };
+    let order: Vec<String> = filters
+        .sort
+        .columns()
+        .iter()
+        .map(|column| format!("{}{}", column, direction))
+        .collect();
+    query.push(" ORDER BY ").push(order.join(", "));
+
+    // unless the distance to a --near position is checked on the rows, the page is taken here:
+    if filters.near.is_none() && (filters.limit.is_some() || filters.offset.is_some()) {
+        query
+            .push(" LIMIT ")
+            .push_bind(filters.limit.map_or(-1, |limit| limit as i64))
+            .push(" OFFSET ")
+            .push_bind(filters.offset.unwrap_or(0) as i64);
+    }
+
+    query
+}
+
+// Writes the query as an SQL condition on the `photos` table.
+fn push_query(builder: &mut QueryBuilder<Sqlite>, query: &Query) {
+    match query {
+        Query::All => {
+            builder.push("1 = 1");
+        }
+        Query::And(queries) | Query::Or(queries) => {
+            let operator = if matches!(query, Query::And(_)) {
+                " AND "
+            } else {
+                " OR "
+            };
+            builder.push("(");
+            for (i, query) in queries.iter().enumerate() {
+                if i > 0 {
+                    builder.push(operator);
+                }
+                push_query(builder, query);
+            }
+            builder.push(")");
+        }
+        // the conditions on missing values (NULL) are false, their negations true:
+        Query::Not(query) => {
+            builder.push("NOT coalesce(");
+            push_query(builder, query);
+            builder.push(", 0)");
+        }
+        Query::Condition(condition) => push_query_condition(builder, condition),
+        // expanded by the callers, an unknown saved query matches nothing:
+        Query::Saved(_) => {
+            builder.push("1 = 0");
+        }
+    }
+}
+
+fn push_query_condition(builder: &mut QueryBuilder<Sqlite>, condition: &Condition) {
+    match condition {
+        Condition::Compare { field, op, value } => {
+            let column = match field {
+                NumberField::Date => "create_day",
+                NumberField::Iso => "iso",
+                NumberField::Aperture => "f_number",
+                NumberField::Focal => "focal_length_mm",
+                NumberField::Focal35mm => "focal_length_35mm",
+                NumberField::Size => "file_size_bytes",
+                NumberField::Rating => "rating",
+                NumberField::Duration => "duration_seconds",
+            };
+            if *field == NumberField::Duration {
+                builder.push("id IN (SELECT photo_id FROM videos WHERE ");
+            }
+            builder.push(format!("{} {} ", column, op.as_sql()));
+            match value {
+                QueryValue::Number(number) => builder.push_bind(*number),
+                QueryValue::Date(date) => builder.push_bind(*date),
+            };
+            if *field == NumberField::Duration {
+                builder.push(")");
+            }
+        }
+        Condition::Gear { kind, name } => {
+            let (raw, normalized) = match kind {
+                GearField::Make => ("make", "normalized_make"),
+                GearField::Model => ("model", "normalized_model"),
+                GearField::Lens => ("lens_model", "normalized_lens"),
+            };
+            builder
+                .push(format!("(instr(lower({}), ", raw))
+                .push_bind(name.to_lowercase())
+                .push(format!(") > 0 OR instr(lower({}), ", normalized))
+                .push_bind(name.to_lowercase())
+                .push(") > 0)");
+        }
+        Condition::Tag(tag) => {
+            builder
+                .push("id IN (SELECT photo_id FROM photo_tags WHERE tag = ")
+                .push_bind(tag.clone())
+                .push(" COLLATE NOCASE)");
+        }
+        Condition::Type(MediaType::Video) => {
+            builder.push("coalesce(mime_type LIKE 'video/%', 0)");
+        }
+        Condition::Type(MediaType::Photo) => {
+            builder.push("NOT coalesce(mime_type LIKE 'video/%', 0)");
+        }
+        Condition::Mime(mime_type) => {
+            match mime_type.strip_suffix("/*") {
+                Some(kind) => builder
+                    .push("mime_type LIKE ")
+                    .push_bind(format!("{}/%", kind)),
+                None => builder.push("mime_type = ").push_bind(mime_type.clone()),
+            };
+        }
+        Condition::Place(place) => {
+            builder.push("(");
+            for (i, column) in ["city", "region", "country", "country_code"]
+                .iter()
+                .enumerate()
+            {
+                if i > 0 {
+                    builder.push(" OR ");
+                }
+                builder
+                    .push(format!("{} = ", column))
+                    .push_bind(place.clone())
+                    .push(" COLLATE NOCASE");
+            }
+            builder.push(")");
+        }
+        Condition::Directory(directory) => {
+            builder
+                .push("substr(directory, 1, length(")
+                .push_bind(directory.clone())
+                .push(")) = ")
+                .push_bind(directory.clone());
+        }
+        Condition::Filename(name) => {
+            builder
+                .push("instr(lower(filename), ")
+                .push_bind(name.to_lowercase())
+                .push(") > 0");
+        }
+        Condition::Hash(hash) => {
+            builder
+                .push("substr(partial_sha256_hash, 1, length(")
+                .push_bind(hash.clone())
+                .push(")) = ")
+                .push_bind(hash.clone());
+        }
+        Condition::Archive(None) => {
+            builder.push("id IN (SELECT item_id FROM archives_items)");
+        }
+        Condition::Archive(Some(archive_id)) => {
+            builder
+                .push("id IN (SELECT item_id FROM archives_items WHERE archive_id = ")
+                .push_bind(archive_id.clone())
+                .push(")");
+        }
+        Condition::Import(Some(import_id)) => {
+            builder.push("import_id = ").push_bind(*import_id);
+        }
+        Condition::Import(None) => {
+            builder.push("import_id = (SELECT max(id) FROM imports)");
+        }
+    }
+}
+
+fn push_meta_condition(query: &mut QueryBuilder<Sqlite>, condition: &MetaCondition) {
+    let negated = condition.operator == MetaOperator::NotEqual;
+    query
+        .push(if negated {
+            " AND id NOT IN"
+        } else {
+            " AND id IN"
+        })
+        .push(" (SELECT photo_id FROM photo_metadata WHERE ");
+
+    match condition.operator {
+        MetaOperator::Equal | MetaOperator::NotEqual => {
+            query
+                .push("CAST(json_extract(photor_inflate(data), ")
+                .push_bind(condition.path())
+                .push(") AS TEXT) = ")
+                .push_bind(condition.value.clone())
+                .push(" COLLATE NOCASE)");
+        }
+        MetaOperator::Contains => {
+            // instr rather than LIKE, for `_` and `%` in the value not to act as wildcards:
+            query
+                .push("instr(lower(CAST(json_extract(photor_inflate(data), ")
+                .push_bind(condition.path())
+                .push(") AS TEXT)), ")
+                .push_bind(condition.value.to_lowercase())
+                .push(") > 0)");
+        }
+    }
 }
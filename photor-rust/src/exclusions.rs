@@ -0,0 +1,232 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Name of the gitignore-style files read in the source trees.
+pub const IGNORE_FILENAME: &str = ".photorignore";
+
+/// The rules used to exclude files and directories from the walk of a source tree: the globs
+/// given on the command line, and the `.photorignore` files found along the way.
+///
+/// The files and the directories excluded by each rule are counted, so that they can be reported
+/// once the walk is over. The excluded directories aren't walked at all, not even to count the
+/// files they hold.
+pub struct Exclusions {
+    cli: Gitignore,
+    // the `.photorignore` matchers by directory, loaded lazily. None when the directory has no
+    // such file.
+    dirs: HashMap<PathBuf, Option<Gitignore>>,
+    counts: BTreeMap<String, ExcludedCount>,
+}
+
+/// How many entries a rule excluded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExcludedCount {
+    pub files: usize,
+    pub directories: usize,
+}
+
+impl ExcludedCount {
+    fn add(&mut self, is_dir: bool) {
+        if is_dir {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+        }
+    }
+}
+
+impl Exclusions {
+    /// Builds the exclusion rules from the `--exclude` globs, which follow the gitignore syntax
+    /// and are matched relatively to the walked directory.
+    pub fn new(globs: &[String]) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new("");
+        for glob in globs {
+            builder
+                .add_line(None, glob)
+                .map_err(|err| format!("Invalid exclude pattern {}: {}", glob, err))?;
+        }
+        let cli = builder
+            .build()
+            .map_err(|err| format!("Invalid exclude patterns: {}", err))?;
+
+        Ok(Exclusions {
+            cli,
+            dirs: HashMap::new(),
+            counts: BTreeMap::new(),
+        })
+    }
+
+    /// Tells whether the given path, found while walking `root`, is excluded. Excluded entries
+    /// are counted.
+    pub fn is_excluded(&mut self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let rule = match self.matching_rule(root, path, is_dir) {
+            Some(rule) => rule,
+            None => return false,
+        };
+
+        debug!("{} excluded by {}", path.display(), rule);
+        self.counts.entry(rule).or_default().add(is_dir);
+
+        true
+    }
+
+    /// Tells whether a file given explicitly (e.g. listed with `--files-from`) is excluded. The
+    /// file is checked as if it had been found walking from the root of its path (the current
+    /// directory for a relative path): each of its parent directories is checked first, then the
+    /// file itself.
+    pub fn is_excluded_listed(&mut self, path: &Path) -> bool {
+        let root = path.ancestors().last().unwrap_or(path);
+        let mut dirs: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| *dir != root)
+            .collect();
+        dirs.reverse();
+
+        dirs.into_iter()
+            .any(|dir| self.is_excluded(root, dir, true))
+            || self.is_excluded(root, path, false)
+    }
+
+    /// Tells whether a member of a zip or tar archive is excluded. Only the `--exclude` globs
+    /// apply to members, matched against their path inside the archive.
+    pub fn is_excluded_member(&mut self, path: &Path) -> bool {
+        let rule = match self.cli.matched_path_or_any_parents(path, false) {
+            Match::Ignore(glob) => format!("`{}` (--exclude)", glob.original()),
+            _ => return false,
+        };
+
+        debug!("{} excluded by {}", path.display(), rule);
+        self.counts.entry(rule).or_default().add(false);
+
+        true
+    }
+
+    /// The number of files and directories excluded so far, by rule.
+    pub fn counts(&self) -> &BTreeMap<String, ExcludedCount> {
+        &self.counts
+    }
+
+    /// Logs how many files and directories were excluded by which rule.
+    pub fn report(&self) {
+        for (rule, count) in &self.counts {
+            match (count.files, count.directories) {
+                (files, 0) => info!("{} file(s) excluded by {}", files, rule),
+                (0, directories) => info!(
+                    "{} directory(ies) excluded by {}, not walked",
+                    directories, rule
+                ),
+                (files, directories) => info!(
+                    "{} file(s) and {} directory(ies) excluded by {}, the directories not walked",
+                    files, directories, rule
+                ),
+            }
+        }
+    }
+
+    // Returns the description of the rule excluding the path, if any. The command line globs are
+    // checked first, then the `.photorignore` files from the deepest directory up to the root,
+    // the first matching one deciding (a `!pattern` re-includes the path).
+    fn matching_rule(&mut self, root: &Path, path: &Path, is_dir: bool) -> Option<String> {
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if let Match::Ignore(glob) = self.cli.matched(relative, is_dir) {
+            return Some(format!("`{}` (--exclude)", glob.original()));
+        }
+
+        let parent = path.parent()?;
+        for dir in parent.ancestors() {
+            if let Some(matcher) = self.matcher(dir) {
+                let relative = path.strip_prefix(dir).unwrap_or(path);
+                match matcher.matched(relative, is_dir) {
+                    Match::Ignore(glob) => {
+                        let from = glob.from().unwrap_or(matcher.path());
+                        return Some(format!("`{}` ({})", glob.original(), from.display()));
+                    }
+                    Match::Whitelist(_) => return None,
+                    Match::None => {}
+                }
+            }
+
+            if dir == root {
+                break;
+            }
+        }
+
+        None
+    }
+
+    fn matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        self.dirs
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| load_ignore_file(dir))
+            .as_ref()
+    }
+}
+
+fn load_ignore_file(dir: &Path) -> Option<Gitignore> {
+    let path = dir.join(IGNORE_FILENAME);
+    if !path.is_file() {
+        return None;
+    }
+
+    let (matcher, err) = Gitignore::new(&path);
+    if let Some(err) = err {
+        warn!("Some rules of {} are invalid: {}", path.display(), err);
+    }
+    Some(matcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_globs() {
+        let mut exclusions =
+            Exclusions::new(&["Exports/".to_string(), "*-edited.jpg".to_string()]).unwrap();
+        let root = Path::new("/nonexistent/card");
+
+        assert!(exclusions.is_excluded(root, &root.join("a/IMG_1-edited.jpg"), false));
+        assert!(exclusions.is_excluded(root, &root.join("b/IMG_2-edited.jpg"), false));
+        assert!(!exclusions.is_excluded(root, &root.join("a/IMG_1.jpg"), false));
+        // directory-only pattern:
+        assert!(!exclusions.is_excluded(root, &root.join("Exports"), false));
+        assert!(exclusions.is_excluded(root, &root.join("Exports"), true));
+
+        assert_eq!(
+            exclusions.counts().get("`*-edited.jpg` (--exclude)"),
+            Some(&ExcludedCount {
+                files: 2,
+                directories: 0
+            })
+        );
+        assert_eq!(
+            exclusions.counts().get("`Exports/` (--exclude)"),
+            Some(&ExcludedCount {
+                files: 0,
+                directories: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_listed_files() {
+        let mut exclusions =
+            Exclusions::new(&["Exports/".to_string(), "*-edited.jpg".to_string()]).unwrap();
+
+        assert!(exclusions.is_excluded_listed(Path::new("/nonexistent/Exports/IMG_1.jpg")));
+        assert!(exclusions.is_excluded_listed(Path::new("card/IMG_1-edited.jpg")));
+        assert!(!exclusions.is_excluded_listed(Path::new("/nonexistent/card/IMG_1.jpg")));
+        assert!(!exclusions.is_excluded_listed(Path::new("IMG_1.jpg")));
+    }
+
+    #[test]
+    fn test_archive_members() {
+        let mut exclusions = Exclusions::new(&["Exports/".to_string()]).unwrap();
+
+        assert!(exclusions.is_excluded_member(Path::new("backup/Exports/IMG_1.JPG")));
+        assert!(!exclusions.is_excluded_member(Path::new("backup/DCIM/IMG_1.JPG")));
+    }
+}
@@ -0,0 +1,133 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Bounds of a numeric value, both included, any of them being optional.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Range {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// Converts an exposure time as written by exiftool, like `1/100`, `0.3`, `2` or `2.5"`, to
+/// seconds.
+pub fn parse_exposure_time(value: &str) -> Option<f64> {
+    let value = value.trim().trim_end_matches(['"', 's']).trim();
+
+    let seconds = match value.split_once('/') {
+        Some((numerator, denominator)) => {
+            numerator.trim().parse::<f64>().ok()? / denominator.trim().parse::<f64>().ok()?
+        }
+        None => value.parse::<f64>().ok()?,
+    };
+
+    Some(seconds).filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+}
+
+/// Converts a focal length as written by exiftool, like `28.0 mm` or `28`, to millimeters. For
+/// the 35mm equivalent tags, the value can also come like `42.0 mm (35 mm equivalent: 63.0 mm)`:
+/// the first number is taken.
+pub fn parse_focal_length(value: &str) -> Option<f64> {
+    lazy_static! {
+        static ref FOCAL_RE: Regex = Regex::new(r"^\s*(\d+(?:\.\d+)?)\s*(?:mm)?").unwrap();
+    }
+
+    let millimeters: f64 = FOCAL_RE.captures(value)?.get(1)?.as_str().parse().ok()?;
+    Some(millimeters).filter(|millimeters| *millimeters > 0.0)
+}
+
+/// Parses an exposure time given on the command line, like `1/30`, `0.5` or `2s`.
+pub fn parse_exposure_arg(value: &str) -> Result<f64, String> {
+    parse_exposure_time(value).ok_or(format!(
+        "invalid exposure time {}, expected something like 1/30, 0.5 or 2s",
+        value
+    ))
+}
+
+/// Parses a range like `20..35`, `20..`, `..35` or a single value like `50`.
+pub fn parse_range(value: &str) -> Result<Range, String> {
+    let invalid = || {
+        format!(
+            "invalid range {}, expected something like 20..35, 20.., ..35 or 50",
+            value
+        )
+    };
+    let parse_bound = |bound: &str| -> Result<Option<f64>, String> {
+        let bound = bound.trim();
+        if bound.is_empty() {
+            return Ok(None);
+        }
+        bound.parse::<f64>().map(Some).map_err(|_| invalid())
+    };
+
+    let range = match value.split_once("..") {
+        Some((min, max)) => Range {
+            min: parse_bound(min)?,
+            max: parse_bound(max)?,
+        },
+        None => {
+            let number = parse_bound(value)?.ok_or_else(invalid)?;
+            Range {
+                min: Some(number),
+                max: Some(number),
+            }
+        }
+    };
+
+    if let (Some(min), Some(max)) = (range.min, range.max) {
+        if min > max {
+            return Err(invalid());
+        }
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_exposure_time() {
+        assert_eq!(parse_exposure_time("1/100"), Some(0.01));
+        assert_eq!(parse_exposure_time("0.3"), Some(0.3));
+        assert_eq!(parse_exposure_time("2.5\""), Some(2.5));
+        assert_eq!(parse_exposure_time("1/0"), None);
+        assert_eq!(parse_exposure_time("Bulb"), None);
+    }
+
+    #[test]
+    fn test_parse_focal_length() {
+        assert_eq!(parse_focal_length("28.0 mm"), Some(28.0));
+        assert_eq!(
+            parse_focal_length("18.3 mm (35 mm equivalent: 28.0 mm)"),
+            Some(18.3)
+        );
+        assert_eq!(parse_focal_length("unknown"), None);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("20..35"),
+            Ok(Range {
+                min: Some(20.0),
+                max: Some(35.0)
+            })
+        );
+        assert_eq!(
+            parse_range("..35"),
+            Ok(Range {
+                min: None,
+                max: Some(35.0)
+            })
+        );
+        assert_eq!(
+            parse_range("50"),
+            Ok(Range {
+                min: Some(50.0),
+                max: Some(50.0)
+            })
+        );
+        assert!(parse_range("35..20").is_err());
+        assert!(parse_range("wide").is_err());
+    }
+}
@@ -1,8 +1,12 @@
+use crate::exclusions::Exclusions;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashSet;
+use std::ffi::OsStr;
 use std::fs;
-use std::path::Path;
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
 use walkdir::{DirEntry, Error as WalkDirError, WalkDir};
 
 lazy_static! {
@@ -37,12 +41,38 @@ pub fn file_size_bytes(filepath: &Path) -> Result<u64, String> {
         .map_err(|err| err.to_string())
 }
 
-pub fn find_photo_files(directory: &Path) -> impl Iterator<Item = DirEntry> {
+/// What to do with the symbolic links met while walking a source directory.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Symlinks are skipped (a warning is logged for each of them).
+    #[default]
+    Ignore,
+    /// Symlinks to files and directories are followed. Loops are detected and reported.
+    Follow,
+    /// Like `follow`, but a file reached through several paths (symlink farms) is yielded only
+    /// once.
+    Once,
+}
+
+/// Walks the directory and yields the photo files found, according to the symlink policy and the
+/// exclusion rules.
+pub fn find_photo_files<'a>(
+    directory: &'a Path,
+    symlinks: SymlinkPolicy,
+    exclusions: &'a mut Exclusions,
+) -> impl Iterator<Item = DirEntry> + 'a {
+    // the canonical paths of the files already yielded, used by SymlinkPolicy::Once:
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+
     WalkDir::new(directory)
         .sort_by_file_name()
         .min_depth(1)
+        .follow_links(symlinks != SymlinkPolicy::Ignore)
         .into_iter()
-        .filter_entry(walker_filter)
+        .filter_entry(move |entry| {
+            walker_filter(entry)
+                && !exclusions.is_excluded(directory, entry.path(), entry.file_type().is_dir())
+        })
         .filter_map(|res| match res {
             // we don't want the iterator to yield directories. Files and symlinks are
             // yielded.
@@ -59,18 +89,45 @@ pub fn find_photo_files(directory: &Path) -> impl Iterator<Item = DirEntry> {
                 None
             }
         })
+        .filter(move |entry| symlinks != SymlinkPolicy::Once || first_visit(&mut seen, entry))
+}
+
+/// Returns true if the file behind the entry hasn't been seen yet, i.e. no other path leading to
+/// the same file has been yielded so far.
+fn first_visit(seen: &mut HashSet<PathBuf>, entry: &DirEntry) -> bool {
+    match fs::canonicalize(entry.path()) {
+        Ok(canonical) => {
+            if seen.insert(canonical.clone()) {
+                true
+            } else {
+                info!(
+                    "{} already reached through another path ({}), skipping...",
+                    entry.path().display(),
+                    canonical.display()
+                );
+                false
+            }
+        }
+        Err(err) => {
+            error!("Failed to resolve {}: {}", entry.path().display(), err);
+            false
+        }
+    }
 }
 
 fn walker_filter(entry: &DirEntry) -> bool {
+    // when symlinks are followed, `file_type` is the one of the link target, so only the links
+    // that are not followed (SymlinkPolicy::Ignore) are symlinks here:
+    if entry.file_type().is_symlink() {
+        warn!(
+            "{} is a symlink, skipping (see the --symlinks option)",
+            entry.path().display()
+        );
+        return false;
+    }
     if entry.file_type().is_file() {
         // if it's a file, it needs to have the extension of an image:
-        let extension = entry
-            .path()
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .map(|ext| ext.to_lowercase())
-            .unwrap_or_default();
-        return PHOTO_FILES_EXTENSIONS.contains(&extension);
+        return has_photo_extension(entry.path());
     }
     if entry.file_type().is_dir() {
         return !entry
@@ -80,20 +137,126 @@ fn walker_filter(entry: &DirEntry) -> bool {
             .unwrap_or(false);
     }
 
-    // TODO what to do with symlinks?
     false
 }
 
+pub fn has_photo_extension(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    PHOTO_FILES_EXTENSIONS.contains(&extension)
+}
+
 fn err_msg(err: WalkDirError) -> String {
     let path = err.path().unwrap_or(Path::new("")).display();
     let base_msg = format!("Failed to access entry {}", path);
-    if let Some(inner) = err.io_error() {
+    if let Some(ancestor) = err.loop_ancestor() {
+        format!(
+            "{}: symlink loop detected (points to {})",
+            base_msg,
+            ancestor.display()
+        )
+    } else if let Some(inner) = err.io_error() {
         format!("{}: {}", base_msg, inner)
     } else {
         format!("{} - unknown error", base_msg)
     }
 }
 
+/// Reads a list of paths from the given file, or from stdin if the path is `-`.
+pub fn read_paths_list(source: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut data = Vec::new();
+    if source == Path::new("-") {
+        io::stdin().read_to_end(&mut data)?;
+    } else {
+        fs::File::open(source)?.read_to_end(&mut data)?;
+    }
+
+    Ok(parse_paths_list(&data))
+}
+
+/// Splits the data into paths. If the data contains a NUL character, the paths are expected to
+/// be NUL-separated, otherwise newline-separated. Empty entries are ignored.
+fn parse_paths_list(data: &[u8]) -> Vec<PathBuf> {
+    let separator = if data.contains(&0) { 0 } else { b'\n' };
+
+    data.split(|byte| *byte == separator)
+        .map(|path| {
+            if separator == b'\n' {
+                path.strip_suffix(b"\r").unwrap_or(path)
+            } else {
+                path
+            }
+        })
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+        .collect()
+}
+
+/// The archive files that can be imported, their members being streamed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    /// Guesses the archive format from the file name, None if it's not a supported archive.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveFormat::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Tells if one of the components of a (relative) path starts with a dot, like the directories
+/// skipped when walking a source directory.
+pub fn is_hidden(path: &Path) -> bool {
+    path.components().any(|component| match component {
+        Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// The directory, at the root of the repository, where the members of archives are extracted
+/// before being moved to their date folder.
+pub const EXTRACTION_DIRECTORY: &str = ".photor-tmp";
+
+/// Writes `head` followed by the rest of the reader to a file named `file_name` in the
+/// extraction directory, and returns its path.
+pub fn extract_to_temp<R: Read>(
+    file_name: &OsStr,
+    head: &[u8],
+    mut rest: R,
+) -> io::Result<PathBuf> {
+    fs::create_dir_all(EXTRACTION_DIRECTORY)?;
+    let path = Path::new(EXTRACTION_DIRECTORY).join(file_name);
+
+    let mut file = fs::File::create(&path)?;
+    let result = file
+        .write_all(head)
+        .and_then(|_| io::copy(&mut rest, &mut file))
+        .and_then(|_| file.sync_all());
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+
+    Ok(path)
+}
+
 pub fn create_date_folder(date: &str) -> std::io::Result<()> {
     let path = Path::new(date);
     if !path.exists() {
@@ -107,6 +270,9 @@ pub fn create_date_folder(date: &str) -> std::io::Result<()> {
 /// in the case where the process is interrupted mid-way:
 /// 1. the file is copied to the destination folder but named with `_temp` as suffix,
 /// 2. on copy completion, the temporary file is renamed to its original file name.
+///
+/// A file of the same name already in the directory is never overwritten: the copy fails.
+///
 /// TODO: deal with the case where a temp file already exists.
 pub fn copy_file_to_date_folder(src: &Path, date: &str) -> Result<(), String> {
     let dest_folder = Path::new(date);
@@ -119,15 +285,82 @@ pub fn copy_file_to_date_folder(src: &Path, date: &str) -> Result<(), String> {
     let dest_path = dest_folder.join(file_name);
     let dest_path_temp = dest_folder.join(&file_name_temp);
 
+    reserve_dest(&dest_path)?;
     match fs::copy(src, &dest_path_temp) {
         Ok(_size) => match fs::rename(&dest_path_temp, &dest_path) {
             Ok(_) => Ok(()),
             Err(err) => {
                 println!("Partially copied file cleanup...");
                 let _ = fs::remove_file(&dest_path_temp);
+                let _ = fs::remove_file(&dest_path);
                 Err(err.to_string())
             }
         },
-        Err(err) => Err(err.to_string()),
+        Err(err) => {
+            let _ = fs::remove_file(&dest_path);
+            Err(err.to_string())
+        }
+    }
+}
+
+/// Moves a file to the directory named after the given date. Unlike `copy_file_to_date_folder`
+/// the source file must be on the same filesystem as the repository (this is used for the files
+/// extracted from archives), the rename being atomic. Like the copy, it never overwrites a file.
+pub fn move_file_to_date_folder(src: &Path, date: &str) -> Result<(), String> {
+    let file_name = src.file_name().unwrap();
+    let dest_path = Path::new(date).join(file_name);
+
+    reserve_dest(&dest_path)?;
+    fs::rename(src, &dest_path).map_err(|err| {
+        let _ = fs::remove_file(&dest_path);
+        err.to_string()
+    })
+}
+
+// Creates the empty file the placed one then replaces, failing if a file of this name is already
+// there. Checking the name and taking it is atomic, so concurrent imports can't overwrite each
+// other's files.
+fn reserve_dest(dest_path: &Path) -> Result<(), String> {
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dest_path)
+        .map(|_| ())
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::AlreadyExists => format!("{} already exists", dest_path.display()),
+            _ => err.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_paths_list_newlines() {
+        let paths = parse_paths_list(b"a/IMG_1.JPG\nb c/IMG_2.JPG\r\n\n");
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("a/IMG_1.JPG"), PathBuf::from("b c/IMG_2.JPG")]
+        );
+    }
+
+    #[test]
+    fn test_archive_format() {
+        let format = |name: &str| ArchiveFormat::from_path(Path::new(name));
+        assert_eq!(format("backup.ZIP"), Some(ArchiveFormat::Zip));
+        assert_eq!(format("a/backup.tar"), Some(ArchiveFormat::Tar));
+        assert_eq!(format("backup.tar.gz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(format("backup.tgz"), Some(ArchiveFormat::TarGz));
+        assert_eq!(format("IMG_1.JPG"), None);
+    }
+
+    #[test]
+    fn test_parse_paths_list_nul() {
+        let paths = parse_paths_list(b"a/IMG\n1.JPG\0b/IMG_2.JPG\0");
+        assert_eq!(
+            paths,
+            vec![PathBuf::from("a/IMG\n1.JPG"), PathBuf::from("b/IMG_2.JPG")]
+        );
     }
 }
@@ -0,0 +1,243 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// The canonical names of the common vendors, found at the start of their EXIF make strings (in
+// upper case).
+const KNOWN_MAKES: &[(&str, &str)] = &[
+    ("APPLE", "Apple"),
+    ("CANON", "Canon"),
+    ("CASIO", "Casio"),
+    ("DJI", "DJI"),
+    ("EASTMAN KODAK", "Kodak"),
+    ("FUJIFILM", "Fujifilm"),
+    ("GOOGLE", "Google"),
+    ("GOPRO", "GoPro"),
+    ("HASSELBLAD", "Hasselblad"),
+    ("HUAWEI", "Huawei"),
+    ("KODAK", "Kodak"),
+    ("KONICA MINOLTA", "Konica Minolta"),
+    ("LEICA", "Leica"),
+    ("MINOLTA", "Minolta"),
+    ("MOTOROLA", "Motorola"),
+    ("NIKON", "Nikon"),
+    ("OLYMPUS", "Olympus"),
+    ("OM DIGITAL SOLUTIONS", "OM System"),
+    ("ONEPLUS", "OnePlus"),
+    ("PANASONIC", "Panasonic"),
+    ("PENTAX", "Pentax"),
+    ("RICOH", "Ricoh"),
+    ("SAMSUNG", "Samsung"),
+    ("SIGMA", "Sigma"),
+    ("SONY", "Sony"),
+    ("XIAOMI", "Xiaomi"),
+];
+
+/// What a gear alias applies to.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GearKind {
+    Make,
+    Model,
+    Lens,
+}
+
+impl GearKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GearKind::Make => "make",
+            GearKind::Model => "model",
+            GearKind::Lens => "lens",
+        }
+    }
+}
+
+/// The normalized names of the camera and lens of a photo.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Gear {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub lens: Option<String>,
+}
+
+impl Gear {
+    /// Normalizes the raw EXIF values. The aliases are applied afterwards, by the database.
+    pub fn normalize(
+        make: Option<&str>,
+        model: Option<&str>,
+        lens_model: Option<&str>,
+        lens_info: Option<&str>,
+    ) -> Self {
+        Gear {
+            make: make.and_then(normalize_make),
+            model: model.and_then(|model| normalize_model(model, make)),
+            lens: normalize_lens(lens_model, lens_info),
+        }
+    }
+
+    pub fn get(&self, kind: GearKind) -> Option<&str> {
+        match kind {
+            GearKind::Make => self.make.as_deref(),
+            GearKind::Model => self.model.as_deref(),
+            GearKind::Lens => self.lens.as_deref(),
+        }
+    }
+
+    pub fn set(&mut self, kind: GearKind, name: String) {
+        match kind {
+            GearKind::Make => self.make = Some(name),
+            GearKind::Model => self.model = Some(name),
+            GearKind::Lens => self.lens = Some(name),
+        }
+    }
+}
+
+/// `RICOH IMAGING COMPANY, LTD.` -> `Ricoh`, `NIKON CORPORATION` -> `Nikon`. The unknown vendors
+/// only lose their company suffixes.
+pub fn normalize_make(make: &str) -> Option<String> {
+    lazy_static! {
+        static ref SUFFIX_RE: Regex = Regex::new(
+            r"(?i)[\s,]+(corporation|corp\.?|company|co\.?|ltd\.?|limited|inc\.?|gmbh|ag|imaging)$"
+        )
+        .unwrap();
+    }
+
+    let make = make.trim();
+    let upper = make.to_uppercase();
+    if let Some((_, name)) = KNOWN_MAKES
+        .iter()
+        .find(|(prefix, _)| starts_with_word(&upper, prefix))
+    {
+        return Some(name.to_string());
+    }
+
+    let mut make = make.to_string();
+    while let Some(found) = SUFFIX_RE.find(&make) {
+        make.truncate(found.start());
+    }
+    Some(make).filter(|make| !make.is_empty())
+}
+
+/// Removes the make repeated at the start of the model by some vendors: `NIKON D750` -> `D750`.
+pub fn normalize_model(model: &str, make: Option<&str>) -> Option<String> {
+    let model = model.trim();
+
+    let prefixes = make
+        .into_iter()
+        .flat_map(|make| {
+            [
+                make.split_whitespace().next().map(str::to_uppercase),
+                normalize_make(make).map(|make| make.to_uppercase()),
+            ]
+        })
+        .flatten();
+    for prefix in prefixes {
+        if let Some(stripped) = strip_word_prefix_uppercase(model, &prefix).map(str::trim) {
+            if !stripped.is_empty() {
+                return Some(stripped.to_string());
+            }
+        }
+    }
+
+    Some(model.to_string()).filter(|model| !model.is_empty())
+}
+
+/// The lens model, or the lens info (like `18.3mm f/2.8`) for the vendors not writing it.
+pub fn normalize_lens(lens_model: Option<&str>, lens_info: Option<&str>) -> Option<String> {
+    let usable = |lens: &&str| {
+        !lens.is_empty()
+            && !lens.chars().all(|c| c == '-')
+            && !lens.eq_ignore_ascii_case("unknown")
+            && !lens.starts_with("0mm")
+            && !lens.starts_with("0.0 mm")
+    };
+
+    let lens_model = lens_model.map(str::trim).filter(usable);
+    let lens_info = lens_info.map(str::trim).filter(usable);
+    lens_model.or(lens_info).map(|lens| {
+        // runs of spaces are found in some lens strings:
+        lens.split_whitespace().collect::<Vec<_>>().join(" ")
+    })
+}
+
+// Whether the text starts with the prefix, followed by the end or a non alphanumeric character.
+fn starts_with_word(text: &str, prefix: &str) -> bool {
+    text.strip_prefix(prefix)
+        .is_some_and(|rest| !rest.starts_with(|c: char| c.is_alphanumeric()))
+}
+
+// Like `starts_with_word` with the uppercased text, but returns the rest of the original text.
+// The text is walked char by char, as uppercasing can change the length of non-ASCII text.
+fn strip_word_prefix_uppercase<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let mut upper = String::new();
+    let mut rest = "";
+    for (index, c) in text.char_indices() {
+        if upper == prefix {
+            rest = &text[index..];
+            break;
+        }
+        upper.extend(c.to_uppercase());
+        if !prefix.starts_with(&upper) {
+            return None;
+        }
+    }
+
+    if upper != prefix || rest.starts_with(|c: char| c.is_alphanumeric()) {
+        return None;
+    }
+    Some(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_make() {
+        assert_eq!(
+            normalize_make("RICOH IMAGING COMPANY, LTD."),
+            Some("Ricoh".to_string())
+        );
+        assert_eq!(
+            normalize_make("NIKON CORPORATION"),
+            Some("Nikon".to_string())
+        );
+        assert_eq!(
+            normalize_make("Acme Optical Co., Ltd."),
+            Some("Acme Optical".to_string())
+        );
+        // not a prefix of a longer word:
+        assert_eq!(normalize_make("Sonyx"), Some("Sonyx".to_string()));
+        assert_eq!(normalize_make("  "), None);
+    }
+
+    #[test]
+    fn test_normalize_model() {
+        assert_eq!(
+            normalize_model("RICOH GR III", Some("RICOH IMAGING COMPANY, LTD.")),
+            Some("GR III".to_string())
+        );
+        assert_eq!(
+            normalize_model("Canon EOS R5", Some("Canon")),
+            Some("EOS R5".to_string())
+        );
+        assert_eq!(
+            normalize_model("X-T4", Some("FUJIFILM")),
+            Some("X-T4".to_string())
+        );
+        // uppercasing changes the length of some characters:
+        assert_eq!(normalize_model("ⱥⱥ 1", Some("ȺȺ")), Some("1".to_string()));
+        assert_eq!(normalize_model("ⱥⱥ1", Some("ȺȺ")), Some("ⱥⱥ1".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_lens() {
+        assert_eq!(
+            normalize_lens(None, Some("18.3mm f/2.8")),
+            Some("18.3mm f/2.8".to_string())
+        );
+        assert_eq!(
+            normalize_lens(Some("XF23mmF2  R WR"), Some("23mm f/2")),
+            Some("XF23mmF2 R WR".to_string())
+        );
+        assert_eq!(normalize_lens(Some("----"), None), None);
+    }
+}
@@ -0,0 +1,233 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// A position in signed decimal degrees (negative for south and west).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// A rectangle of coordinates, in signed decimal degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub min_longitude: f64,
+    pub max_latitude: f64,
+    pub max_longitude: f64,
+}
+
+impl Point {
+    /// The great-circle distance to the other point, in meters (haversine formula).
+    pub fn distance_meters(&self, other: &Point) -> f64 {
+        let dlat = (other.latitude - self.latitude).to_radians();
+        let dlon = (other.longitude - self.longitude).to_radians();
+        let a = (dlat / 2.0).sin().powi(2)
+            + self.latitude.to_radians().cos()
+                * other.latitude.to_radians().cos()
+                * (dlon / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+    }
+
+    /// A bounding box containing all the points within the radius, used to pre-select the photos
+    /// with the location index before the exact distance is checked.
+    pub fn bounding_box(&self, radius_meters: f64) -> BoundingBox {
+        let dlat = (radius_meters / EARTH_RADIUS_METERS).to_degrees();
+        // the degrees of longitude shrink with the latitude (the cosine is bounded to not divide
+        // by zero at the poles):
+        let dlon = dlat / self.latitude.to_radians().cos().max(0.01);
+
+        BoundingBox {
+            min_latitude: self.latitude - dlat,
+            min_longitude: self.longitude - dlon,
+            max_latitude: self.latitude + dlat,
+            max_longitude: self.longitude + dlon,
+        }
+    }
+}
+
+/// Parses `LAT,LON` in decimal degrees, like `38.7139,-9.1334`.
+pub fn parse_point(value: &str) -> Result<Point, String> {
+    let numbers = parse_numbers(value, 2)?;
+    let point = Point {
+        latitude: numbers[0],
+        longitude: numbers[1],
+    };
+    check_point(&point)?;
+
+    Ok(point)
+}
+
+/// Parses `MINLAT,MINLON,MAXLAT,MAXLON` in decimal degrees.
+pub fn parse_bounding_box(value: &str) -> Result<BoundingBox, String> {
+    let numbers = parse_numbers(value, 4)?;
+    let bbox = BoundingBox {
+        min_latitude: numbers[0],
+        min_longitude: numbers[1],
+        max_latitude: numbers[2],
+        max_longitude: numbers[3],
+    };
+    check_point(&Point {
+        latitude: bbox.min_latitude,
+        longitude: bbox.min_longitude,
+    })?;
+    check_point(&Point {
+        latitude: bbox.max_latitude,
+        longitude: bbox.max_longitude,
+    })?;
+    if bbox.min_latitude > bbox.max_latitude || bbox.min_longitude > bbox.max_longitude {
+        return Err("expected the south-west corner first, then the north-east one".to_string());
+    }
+
+    Ok(bbox)
+}
+
+/// Parses a distance like `2km`, `500m` or `500` (meters), and returns it in meters.
+pub fn parse_distance(value: &str) -> Result<f64, String> {
+    let value = value.trim().to_lowercase();
+    let (number, factor) = if let Some(number) = value.strip_suffix("km") {
+        (number, 1000.0)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 1.0)
+    } else {
+        (value.as_str(), 1.0)
+    };
+
+    match number.trim().parse::<f64>() {
+        Ok(distance) if distance >= 0.0 => Ok(distance * factor),
+        _ => Err(format!(
+            "invalid distance {}, expected something like 2km or 500m",
+            value
+        )),
+    }
+}
+
+fn parse_numbers(value: &str, count: usize) -> Result<Vec<f64>, String> {
+    let numbers = value
+        .split(',')
+        .map(|number| number.trim().parse::<f64>())
+        .collect::<Result<Vec<f64>, _>>()
+        .map_err(|err| format!("invalid coordinates {}: {}", value, err))?;
+
+    if numbers.len() != count {
+        return Err(format!(
+            "invalid coordinates {}: expected {} comma-separated numbers",
+            value, count
+        ));
+    }
+
+    Ok(numbers)
+}
+
+fn check_point(point: &Point) -> Result<(), String> {
+    if !(-90.0..=90.0).contains(&point.latitude) || !(-180.0..=180.0).contains(&point.longitude) {
+        return Err(format!(
+            "coordinates out of range: {},{}",
+            point.latitude, point.longitude
+        ));
+    }
+    Ok(())
+}
+
+/// Converts a latitude or longitude as written by exiftool, like `38 deg 42' 50.04" N` or
+/// `38.714`, to signed decimal degrees. The hemisphere is taken from the value itself if it ends
+/// with a cardinal point, or from the reference tag (`North`, `S`...).
+pub fn parse_coordinate(value: &str, reference: Option<&str>) -> Option<f64> {
+    lazy_static! {
+        static ref DMS_RE: Regex = Regex::new(
+            r#"^([+-]?[\d.]+)(?:\s*deg\s*([\d.]+)'?(?:\s*([\d.]+)"?)?)?\s*([NSEWnsew])?$"#
+        )
+        .unwrap();
+    }
+
+    let captures = DMS_RE.captures(value.trim())?;
+    let degrees: f64 = captures.get(1)?.as_str().parse().ok()?;
+    let minutes: f64 = captures
+        .get(2)
+        .map_or(Some(0.0), |m| m.as_str().parse().ok())?;
+    let seconds: f64 = captures
+        .get(3)
+        .map_or(Some(0.0), |s| s.as_str().parse().ok())?;
+
+    let absolute = degrees.abs() + minutes / 60.0 + seconds / 3600.0;
+
+    let hemisphere = captures
+        .get(4)
+        .map(|m| m.as_str())
+        .or(reference)
+        .and_then(|r| r.trim().chars().next())
+        .map(|c| c.to_ascii_uppercase());
+    let negative = degrees < 0.0 || matches!(hemisphere, Some('S') | Some('W'));
+
+    Some(if negative { -absolute } else { absolute })
+}
+
+/// Converts an altitude as written by exiftool, like `52.3 m Above Sea Level` or `52.3`, to
+/// meters, negative below the sea level.
+pub fn parse_altitude(value: &str, reference: Option<&str>) -> Option<f64> {
+    let meters = leading_number(value)?;
+    let below = value.contains("Below")
+        || reference.is_some_and(|r| r.contains("Below") || r.trim() == "1");
+
+    Some(if below { -meters.abs() } else { meters })
+}
+
+/// Converts a direction (like `GPSImgDirection`) to degrees.
+pub fn parse_direction(value: &str) -> Option<f64> {
+    leading_number(value)
+}
+
+fn leading_number(value: &str) -> Option<f64> {
+    lazy_static! {
+        static ref NUMBER_RE: Regex = Regex::new(r"^\s*([+-]?\d+(?:\.\d+)?)").unwrap();
+    }
+
+    NUMBER_RE.captures(value)?.get(1)?.as_str().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_coordinate() {
+        let lat = parse_coordinate(r#"38 deg 42' 50.04" N"#, None).unwrap();
+        assert!((lat - 38.7139).abs() < 1e-6);
+
+        let lon = parse_coordinate(r#"9 deg 8' 0.24""#, Some("West")).unwrap();
+        assert!((lon + 9.1334).abs() < 1e-6);
+
+        assert_eq!(parse_coordinate("-33.5", None), Some(-33.5));
+        assert_eq!(parse_coordinate("not a coordinate", None), None);
+    }
+
+    #[test]
+    fn test_parse_altitude() {
+        assert_eq!(parse_altitude("52.3 m Above Sea Level", None), Some(52.3));
+        assert_eq!(parse_altitude("12 m", Some("Below Sea Level")), Some(-12.0));
+    }
+
+    #[test]
+    fn test_distance() {
+        let lisbon = Point {
+            latitude: 38.7223,
+            longitude: -9.1393,
+        };
+        let porto = Point {
+            latitude: 41.1579,
+            longitude: -8.6291,
+        };
+        let distance = lisbon.distance_meters(&porto);
+        assert!((distance - 274_000.0).abs() < 2_000.0);
+    }
+
+    #[test]
+    fn test_parse_distance() {
+        assert_eq!(parse_distance("2km"), Ok(2000.0));
+        assert_eq!(parse_distance("500m"), Ok(500.0));
+        assert!(parse_distance("far").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::Path;
+
+/// A point of a GPX track.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+}
+
+/// The position found for a given time on a track.
+#[derive(Debug, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+    /// The time between the requested time and the nearest track point.
+    pub gap: Duration,
+}
+
+/// The timed points of one or several GPX files, sorted by time.
+pub struct Track {
+    points: Vec<TrackPoint>,
+}
+
+impl Track {
+    pub fn new(mut points: Vec<TrackPoint>) -> Self {
+        points.sort_by_key(|point| point.time);
+        Track { points }
+    }
+
+    /// The times of the first and last points.
+    pub fn time_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        Some((self.points.first()?.time, self.points.last()?.time))
+    }
+
+    /// Finds the position at the given time. Between two points closer in time than `max_gap`,
+    /// the position is interpolated. Otherwise (the recording was paused, or the time is out of the
+    /// track) the nearest point is used if it's closer than `max_gap`.
+    pub fn locate(&self, time: DateTime<Utc>, max_gap: Duration) -> Option<Location> {
+        let index = self.points.partition_point(|point| point.time <= time);
+        let before = index.checked_sub(1).and_then(|i| self.points.get(i));
+        let after = self.points.get(index);
+
+        if let (Some(before), Some(after)) = (before, after) {
+            if after.time - before.time <= max_gap {
+                return Some(interpolate(before, after, time));
+            }
+        }
+
+        [before, after]
+            .into_iter()
+            .flatten()
+            .map(|point| (point, (point.time - time).abs()))
+            .filter(|(_, gap)| *gap <= max_gap)
+            .min_by_key(|(_, gap)| *gap)
+            .map(|(point, gap)| Location {
+                latitude: point.latitude,
+                longitude: point.longitude,
+                elevation: point.elevation,
+                gap,
+            })
+    }
+}
+
+fn interpolate(before: &TrackPoint, after: &TrackPoint, time: DateTime<Utc>) -> Location {
+    let span = (after.time - before.time).num_milliseconds();
+    let ratio = if span == 0 {
+        0.0
+    } else {
+        (time - before.time).num_milliseconds() as f64 / span as f64
+    };
+    let between = |a: f64, b: f64| a + (b - a) * ratio;
+
+    Location {
+        latitude: between(before.latitude, after.latitude),
+        longitude: between(before.longitude, after.longitude),
+        elevation: match (before.elevation, after.elevation) {
+            (Some(a), Some(b)) => Some(between(a, b)),
+            (a, b) => a.or(b),
+        },
+        gap: (time - before.time).min(after.time - time),
+    }
+}
+
+// A track point being parsed.
+struct PartialPoint {
+    latitude: f64,
+    longitude: f64,
+    elevation: Option<f64>,
+    time: Option<DateTime<Utc>>,
+}
+
+pub fn read(path: &Path) -> Result<Vec<TrackPoint>, String> {
+    let data = std::fs::read(path).map_err(|err| err.to_string())?;
+    parse(&data)
+}
+
+/// Parses the points of the tracks (`trkpt`), routes (`rtept`) and the waypoints (`wpt`) of a GPX
+/// document. The points without time are ignored, they can't be matched with photos.
+pub fn parse(data: &[u8]) -> Result<Vec<TrackPoint>, String> {
+    let mut reader = Reader::from_reader(data);
+    let mut buf = Vec::new();
+
+    let mut points = Vec::new();
+    // the point being read, and the current element in it:
+    let mut current: Option<PartialPoint> = None;
+    let mut element: Vec<u8> = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Err(err) => {
+                return Err(format!(
+                    "Invalid GPX at position {}: {}",
+                    reader.buffer_position(),
+                    err
+                ))
+            }
+            Ok(Event::Eof) => break,
+            Ok(Event::Start(tag)) => match tag.local_name().as_ref() {
+                b"trkpt" | b"rtept" | b"wpt" => {
+                    let mut latitude = None;
+                    let mut longitude = None;
+                    for attribute in tag.attributes().flatten() {
+                        let value = String::from_utf8_lossy(&attribute.value)
+                            .parse::<f64>()
+                            .ok();
+                        match attribute.key.local_name().as_ref() {
+                            b"lat" => latitude = value,
+                            b"lon" => longitude = value,
+                            _ => {}
+                        }
+                    }
+                    current = latitude
+                        .zip(longitude)
+                        .map(|(latitude, longitude)| PartialPoint {
+                            latitude,
+                            longitude,
+                            elevation: None,
+                            time: None,
+                        });
+                }
+                name => element = name.to_vec(),
+            },
+            Ok(Event::Text(text)) => {
+                if let Some(point) = current.as_mut() {
+                    let text = text.unescape().map_err(|err| err.to_string())?;
+                    match element.as_slice() {
+                        b"ele" => point.elevation = text.trim().parse().ok(),
+                        b"time" => point.time = parse_time(text.trim()),
+                        _ => {}
+                    }
+                }
+            }
+            Ok(Event::End(tag)) => match tag.local_name().as_ref() {
+                b"trkpt" | b"rtept" | b"wpt" => {
+                    if let Some(point) = current.take() {
+                        if let Some(time) = point.time {
+                            points.push(TrackPoint {
+                                time,
+                                latitude: point.latitude,
+                                longitude: point.longitude,
+                                elevation: point.elevation,
+                            });
+                        }
+                    }
+                }
+                _ => element.clear(),
+            },
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(points)
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .ok()
+        // some tools omit the timezone, UTC is then assumed:
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+                .map(|time| time.and_utc())
+                .ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPX: &[u8] = br#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="38.7000" lon="-9.1000"><ele>10</ele><time>2023-05-04T10:00:00Z</time></trkpt>
+    <trkpt lat="38.7100" lon="-9.1200"><ele>20</ele><time>2023-05-04T10:01:00Z</time></trkpt>
+    <trkpt lat="38.8000" lon="-9.2000"><time>2023-05-04T11:00:00Z</time></trkpt>
+    <trkpt lat="38.9000" lon="-9.3000"></trkpt>
+  </trkseg></trk>
+</gpx>"#;
+
+    fn time(value: &str) -> DateTime<Utc> {
+        parse_time(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let points = parse(GPX).unwrap();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].elevation, Some(10.0));
+        assert_eq!(points[1].time, time("2023-05-04T10:01:00Z"));
+    }
+
+    #[test]
+    fn test_locate() {
+        let track = Track::new(parse(GPX).unwrap());
+        let max_gap = Duration::minutes(5);
+
+        // interpolated:
+        let location = track.locate(time("2023-05-04T10:00:30Z"), max_gap).unwrap();
+        assert!((location.latitude - 38.705).abs() < 1e-9);
+        assert_eq!(location.elevation, Some(15.0));
+        assert_eq!(location.gap, Duration::seconds(30));
+
+        // in the hole of the track, close to its start:
+        let location = track.locate(time("2023-05-04T10:04:00Z"), max_gap).unwrap();
+        assert_eq!(location.latitude, 38.71);
+
+        // in the hole of the track, far from both points:
+        assert_eq!(track.locate(time("2023-05-04T10:30:00Z"), max_gap), None);
+        // out of the track:
+        assert_eq!(track.locate(time("2023-05-04T09:00:00Z"), max_gap), None);
+    }
+}
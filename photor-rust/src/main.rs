@@ -3,13 +3,28 @@ extern crate log;
 
 use env_logger::Env;
 
+pub mod annotations;
+pub mod catalog;
 pub mod checksum;
 pub mod cli;
 pub mod commands;
 pub mod database;
+pub mod exclusions;
+pub mod exposure;
 pub mod files;
+pub mod gear;
+pub mod gps;
+pub mod gpx;
+pub mod metadata;
 pub mod models;
+pub mod output;
 pub mod photoexif;
+pub mod places;
+pub mod query;
+pub mod schema;
+pub mod takeout;
+pub mod units;
+pub mod video;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> anyhow::Result<()> {
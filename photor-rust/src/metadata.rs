@@ -0,0 +1,177 @@
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use lazy_static::lazy_static;
+use libsqlite3_sys as ffi;
+use regex::Regex;
+use serde_json::Value as JsonValue;
+use sqlx::sqlite::SqliteConnection;
+use std::ffi::{c_int, CStr};
+use std::io::{Read, Write};
+
+/// The name of the SQL function uncompressing the `photo_metadata.data` blobs to JSON text.
+pub const INFLATE_FUNCTION: &CStr = c"photor_inflate";
+
+/// Compresses the exiftool output of a photo, to be stored in `photo_metadata`.
+pub fn compress(metadata: &JsonValue) -> Result<Vec<u8>, String> {
+    let json = serde_json::to_vec(metadata).map_err(|err| err.to_string())?;
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json).map_err(|err| err.to_string())?;
+    encoder.finish().map_err(|err| err.to_string())
+}
+
+pub fn decompress(data: &[u8]) -> Result<String, String> {
+    let mut json = String::new();
+    ZlibDecoder::new(data)
+        .read_to_string(&mut json)
+        .map_err(|err| err.to_string())?;
+    Ok(json)
+}
+
+/// Registers the `photor_inflate()` SQL function on the connection, so that the stored metadata
+/// can be queried with the SQLite JSON functions.
+pub async fn register_functions(conn: &mut SqliteConnection) -> Result<(), sqlx::Error> {
+    let mut handle = conn.lock_handle().await?;
+
+    // SAFETY: the handle is a valid connection for the duration of the lock, and the function
+    // doesn't keep any reference to the values it's given.
+    let result = unsafe {
+        ffi::sqlite3_create_function_v2(
+            handle.as_raw_handle().as_ptr(),
+            INFLATE_FUNCTION.as_ptr(),
+            1,
+            ffi::SQLITE_UTF8 | ffi::SQLITE_DETERMINISTIC,
+            std::ptr::null_mut(),
+            Some(inflate),
+            None,
+            None,
+            None,
+        )
+    };
+
+    if result != ffi::SQLITE_OK {
+        return Err(sqlx::Error::Protocol(format!(
+            "failed to register the {:?} SQL function (code {})",
+            INFLATE_FUNCTION, result
+        )));
+    }
+    Ok(())
+}
+
+// photor_inflate(blob): the JSON text of a compressed blob, NULL for a NULL.
+unsafe extern "C" fn inflate(
+    context: *mut ffi::sqlite3_context,
+    argc: c_int,
+    argv: *mut *mut ffi::sqlite3_value,
+) {
+    if argc != 1 {
+        ffi::sqlite3_result_error(context, c"expected one argument".as_ptr(), -1);
+        return;
+    }
+
+    let value = *argv;
+    if ffi::sqlite3_value_type(value) == ffi::SQLITE_NULL {
+        ffi::sqlite3_result_null(context);
+        return;
+    }
+
+    let blob = ffi::sqlite3_value_blob(value) as *const u8;
+    let length = ffi::sqlite3_value_bytes(value) as usize;
+    let data = if blob.is_null() {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(blob, length)
+    };
+
+    match decompress(data) {
+        Ok(json) => ffi::sqlite3_result_text(
+            context,
+            json.as_ptr() as *const _,
+            json.len() as c_int,
+            ffi::SQLITE_TRANSIENT(),
+        ),
+        Err(_) => ffi::sqlite3_result_error(context, c"invalid compressed metadata".as_ptr(), -1),
+    }
+}
+
+/// How a metadata value is compared by a `--where-meta` condition.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MetaOperator {
+    /// `KEY=VALUE`: equal, ignoring the case.
+    Equal,
+    /// `KEY!=VALUE`: different, or missing.
+    NotEqual,
+    /// `KEY~VALUE`: containing the value, ignoring the case.
+    Contains,
+}
+
+/// A condition on a field of the exiftool output, like `Flash=Fired`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetaCondition {
+    pub key: String,
+    pub operator: MetaOperator,
+    pub value: String,
+}
+
+impl MetaCondition {
+    /// The JSON path of the field, to give to `json_extract()`.
+    pub fn path(&self) -> String {
+        format!("$.\"{}\"", self.key)
+    }
+}
+
+/// Parses `KEY=VALUE`, `KEY!=VALUE` or `KEY~VALUE`, the keys being exiftool tag names like
+/// `Flash` or `FilmMode`.
+pub fn parse_condition(value: &str) -> Result<MetaCondition, String> {
+    lazy_static! {
+        static ref CONDITION_RE: Regex =
+            Regex::new(r"^\s*([A-Za-z0-9_:-]+)\s*(!=|=|~)\s*(.*)$").unwrap();
+    }
+
+    let captures = CONDITION_RE.captures(value).ok_or(format!(
+        "invalid condition {}, expected something like Flash=Fired",
+        value
+    ))?;
+
+    let operator = match &captures[2] {
+        "=" => MetaOperator::Equal,
+        "!=" => MetaOperator::NotEqual,
+        _ => MetaOperator::Contains,
+    };
+
+    Ok(MetaCondition {
+        key: captures[1].to_string(),
+        operator,
+        value: captures[3].trim().to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress() {
+        let metadata = serde_json::json!({"Flash": "Fired", "ISO": 200});
+        let json = decompress(&compress(&metadata).unwrap()).unwrap();
+        assert_eq!(serde_json::from_str::<JsonValue>(&json).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(
+            parse_condition("Flash=Fired"),
+            Ok(MetaCondition {
+                key: "Flash".to_string(),
+                operator: MetaOperator::Equal,
+                value: "Fired".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_condition("FilmMode != Classic Chrome").map(|c| c.operator),
+            Ok(MetaOperator::NotEqual)
+        );
+        assert!(parse_condition("Flash").is_err());
+        assert!(parse_condition("$.x\"=1").is_err());
+    }
+}
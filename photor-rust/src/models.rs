@@ -1,4 +1,8 @@
-#[derive(sqlx::FromRow, Debug)]
+use crate::places::Place;
+use chrono::{NaiveDate, NaiveDateTime};
+use std::fmt;
+
+#[derive(sqlx::FromRow, Debug, Default, serde::Serialize)]
 pub struct Photo {
     pub id: i64,
     pub filename: String,
@@ -18,11 +22,88 @@ pub struct Photo {
     pub lens_info: Option<String>,
     pub lens_make: Option<String>,
     pub lens_model: Option<String>,
-    pub create_date: String,
-    pub create_day: Option<String>,
-    pub inserted_at: String,
+    /// In the camera time.
+    pub create_date: NaiveDateTime,
+    pub create_day: Option<NaiveDate>,
+    /// In UTC.
+    pub inserted_at: NaiveDateTime,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    pub caption: Option<String>,
+    pub gps_direction: Option<f64>,
+    pub location_source: Option<String>,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub exposure_seconds: Option<f64>,
+    pub f_number: Option<f64>,
+    pub focal_length_mm: Option<f64>,
+    pub focal_length_35mm: Option<f64>,
+    pub normalized_make: Option<String>,
+    pub normalized_model: Option<String>,
+    pub normalized_lens: Option<String>,
+    pub camera_serial: Option<String>,
+    pub lens_serial: Option<String>,
+    pub shutter_count: Option<i64>,
+    pub orientation: Option<i64>,
+    pub display_width: Option<i64>,
+    pub display_height: Option<i64>,
+    pub rating: Option<i64>,
+    pub color_label: Option<String>,
 }
 
+/// The columns of the photos, in the order of their fields, as listed by `photor list`.
+pub const PHOTO_COLUMNS: &[&str] = &[
+    "id",
+    "filename",
+    "directory",
+    "partial_sha256_hash",
+    "full_sha256_hash",
+    "file_size_bytes",
+    "image_height",
+    "image_width",
+    "mime_type",
+    "iso",
+    "aperture",
+    "shutter_speed",
+    "focal_length",
+    "make",
+    "model",
+    "lens_info",
+    "lens_make",
+    "lens_model",
+    "create_date",
+    "create_day",
+    "inserted_at",
+    "latitude",
+    "longitude",
+    "altitude",
+    "caption",
+    "gps_direction",
+    "location_source",
+    "country_code",
+    "country",
+    "region",
+    "city",
+    "exposure_seconds",
+    "f_number",
+    "focal_length_mm",
+    "focal_length_35mm",
+    "normalized_make",
+    "normalized_model",
+    "normalized_lens",
+    "camera_serial",
+    "lens_serial",
+    "shutter_count",
+    "orientation",
+    "display_width",
+    "display_height",
+    "rating",
+    "color_label",
+];
+
 pub struct NewPhoto {
     // ------------------------------
     // file:
@@ -30,15 +111,20 @@ pub struct NewPhoto {
     pub filename: String,
     pub directory: String,
     pub file_size_bytes: i64,
-    pub image_height: Option<i32>,
-    pub image_width: Option<i32>,
+    pub image_height: Option<i64>,
+    pub image_width: Option<i64>,
     pub mime_type: Option<String>,
     // // ------------------------------
     // // Shot:
-    pub iso: Option<i32>,
-    pub aperture: Option<f32>,
+    pub iso: Option<i64>,
+    pub aperture: Option<f64>,
     pub shutter_speed: Option<String>,
     pub focal_length: Option<String>,
+    // the numeric values, for the range queries:
+    pub exposure_seconds: Option<f64>,
+    pub f_number: Option<f64>,
+    pub focal_length_mm: Option<f64>,
+    pub focal_length_35mm: Option<f64>,
 
     // // ------------------------------
     // // Camera:
@@ -50,6 +136,230 @@ pub struct NewPhoto {
     pub lens_info: Option<String>,
     pub lens_make: Option<String>,
     pub lens_model: Option<String>,
+    pub camera_serial: Option<String>,
+    pub lens_serial: Option<String>,
+    pub shutter_count: Option<i64>,
+
+    // // ------------------------------
+    // // Location:
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    pub gps_direction: Option<f64>,
+    /// `exif`, `takeout` or `gpx`.
+    pub location_source: Option<String>,
+    pub place: Place,
+
+    // // ------------------------------
+    // // Annotations:
+    pub orientation: Option<i64>,
+    pub display_width: Option<i64>,
+    pub display_height: Option<i64>,
+    pub rating: Option<i64>,
+    pub color_label: Option<String>,
+    pub caption: Option<String>,
+    /// Stored in `photo_tags`.
+    pub tags: Vec<String>,
+
+    pub create_date: NaiveDateTime,
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct Video {
+    pub photo_id: i64,
+    pub duration_seconds: Option<f64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    pub bitrate: Option<i64>,
+    pub rotation: Option<i64>,
+    pub display_width: Option<i64>,
+    pub display_height: Option<i64>,
+}
+
+impl Video {
+    /// The metadata, without the photo id, to be compared with the one read from a file.
+    pub fn to_new_video(&self) -> NewVideo {
+        NewVideo {
+            duration_seconds: self.duration_seconds,
+            video_codec: self.video_codec.clone(),
+            audio_codec: self.audio_codec.clone(),
+            frame_rate: self.frame_rate,
+            bitrate: self.bitrate,
+            rotation: self.rotation,
+            display_width: self.display_width,
+            display_height: self.display_height,
+        }
+    }
+}
+
+/// The video specific metadata of a file being imported, stored in the `videos` table next to its
+/// `photos` row.
+#[derive(Debug, PartialEq)]
+pub struct NewVideo {
+    pub duration_seconds: Option<f64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub frame_rate: Option<f64>,
+    /// In bits per second.
+    pub bitrate: Option<i64>,
+    /// Clockwise, in degrees.
+    pub rotation: Option<i64>,
+    pub display_width: Option<i64>,
+    pub display_height: Option<i64>,
+}
+
+/// A number of photos taken at some place, as counted by `photor places`.
+#[derive(sqlx::FromRow, Debug)]
+pub struct PlaceCount {
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub count: i64,
+}
+
+/// The totals of the repository shown by `photor stats`.
+pub struct Stats {
+    pub photos: i64,
+    pub videos: i64,
+    pub total_size_bytes: i64,
+    pub total_video_seconds: f64,
+}
+
+/// A number of photos taken with some gear (make, model or lens), with the raw EXIF values
+/// normalized to its name.
+#[derive(sqlx::FromRow)]
+pub struct GearCount {
+    pub name: Option<String>,
+    pub count: i64,
+    /// Separated by newlines.
+    pub raw_values: Option<String>,
+}
+
+/// The usage of a camera body, identified by its make, model and serial number.
+#[derive(sqlx::FromRow)]
+pub struct BodyUsage {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub photos: i64,
+    pub first_shot: NaiveDateTime,
+    pub last_shot: NaiveDateTime,
+    /// The highest shutter count read, and the date of its photo.
+    pub shutter_count: Option<i64>,
+    pub shutter_count_date: Option<NaiveDateTime>,
+    /// The highest shutter count, plus the photos taken by the body afterwards.
+    pub estimated_shutter_count: Option<i64>,
+}
+
+/// The number of photos taken by a camera body in a year.
+#[derive(sqlx::FromRow)]
+pub struct YearUsage {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub year: String,
+    pub photos: i64,
+}
+
+/// A number of videos encoded with some codec.
+pub struct CodecCount {
+    pub video_codec: Option<String>,
+    pub count: i64,
+    pub duration_seconds: f64,
+}
+
+/// The value of a `photos` column, as compared and updated by `photor metadata refresh`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnValue {
+    Integer(Option<i64>),
+    Real(Option<f64>),
+    Text(Option<String>),
+}
+
+impl fmt::Display for ColumnValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnValue::Integer(Some(value)) => write!(f, "{}", value),
+            ColumnValue::Real(Some(value)) => write!(f, "{}", value),
+            ColumnValue::Text(Some(value)) => write!(f, "{:?}", value),
+            _ => write!(f, "NULL"),
+        }
+    }
+}
+
+/// Where a photo is in the repository.
+#[derive(sqlx::FromRow, Debug)]
+pub struct PhotoFile {
+    pub directory: String,
+    pub filename: String,
+    pub partial_sha256_hash: String,
+}
+
+/// A migration applied to the database, as recorded by sqlx.
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct MigrationRecord {
+    pub version: i64,
+    pub description: String,
+    pub installed_on: NaiveDateTime,
+    pub success: bool,
+    pub checksum: Vec<u8>,
+}
+
+/// What a catalog import added to the database.
+#[derive(Debug, Default)]
+pub struct CatalogCounts {
+    pub photos: u64,
+    pub photo_tags: u64,
+    pub archives: u64,
+    pub archive_tags: u64,
+    pub archive_items: u64,
+    /// The archive items whose photo isn't in the database nor in the dump.
+    pub skipped_items: u64,
+}
+
+/// A query saved by name, to be reused with `@name`.
+#[derive(sqlx::FromRow, Debug)]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// The import of the web app a photo was imported by.
+#[derive(sqlx::FromRow, Debug)]
+pub struct PhotoImport {
+    pub id: i64,
+    pub started_at: String,
+}
+
+/// An archive holding a photo, and when the photo was added to it.
+#[derive(sqlx::FromRow, Debug)]
+pub struct PhotoArchive {
+    pub archive_id: String,
+    pub media_type: String,
+    pub inserted_at: String,
+}
+
+/// A thumbnail of a photo, made by the web app.
+#[derive(sqlx::FromRow, Debug)]
+pub struct Thumbnail {
+    pub size_name: String,
+    pub width: i64,
+    pub height: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub create_date: String,
+    #[test]
+    fn test_photo_columns() {
+        let photo = serde_json::to_value(Photo::default()).unwrap();
+        let mut fields: Vec<&String> = photo.as_object().unwrap().keys().collect();
+        let mut columns: Vec<&str> = PHOTO_COLUMNS.to_vec();
+        fields.sort();
+        columns.sort();
+        assert_eq!(fields, columns);
+    }
 }
@@ -0,0 +1,338 @@
+use serde_json::Value as JsonValue;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The formats of the listing commands.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    /// Aligned columns, for people
+    Table,
+    /// An array of objects
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// With a header line
+    Csv,
+    /// The paths of the files, one per line
+    Paths,
+    /// The paths of the files, separated by NUL characters (for `xargs -0`)
+    Paths0,
+}
+
+/// The rows of a small listing, printed at once by `print`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Listing {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<JsonValue>>,
+}
+
+impl Listing {
+    pub fn new(columns: &[&str]) -> Self {
+        Listing {
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Prints a listing in the format, with the given columns only (all of them if none is given).
+pub fn print(listing: Listing, format: OutputFormat, columns: &[String]) -> anyhow::Result<()> {
+    let names: Vec<&str> = listing.columns.iter().map(String::as_str).collect();
+    let mut printer = Printer::new(std::io::stdout().lock(), format, &names, columns)?;
+    for row in &listing.rows {
+        printer.row(row, None)?;
+    }
+    printer.finish()
+}
+
+/// Whether the error is the output being closed, like by `photor list | head`: the command can
+/// stop there.
+pub fn is_closed(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+// The number of rows read to size the columns of a table, the next ones being printed as they
+// come (their cells being wider if needed).
+const TABLE_SAMPLE: usize = 1000;
+
+/// Prints the rows of a listing as they come, whatever their number.
+pub struct Printer<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    columns: Vec<String>,
+    // the positions of the printed columns in the rows:
+    indexes: Vec<usize>,
+    count: usize,
+    // the first rows of a table, until its columns are sized:
+    sample: Vec<Vec<String>>,
+    widths: Option<Vec<usize>>,
+    numeric: Vec<bool>,
+}
+
+impl<W: Write> Printer<W> {
+    /// A printer of rows having the given columns, printing the `selected` ones (all of them if
+    /// none is given).
+    pub fn new(
+        writer: W,
+        format: OutputFormat,
+        columns: &[&str],
+        selected: &[String],
+    ) -> anyhow::Result<Self> {
+        let selected: Vec<String> = if selected.is_empty() {
+            columns.iter().map(|column| column.to_string()).collect()
+        } else {
+            selected.to_vec()
+        };
+        let indexes = selected
+            .iter()
+            .map(|name| {
+                columns
+                    .iter()
+                    .position(|column| column == name)
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Unknown column {}, expected one of: {}",
+                            name,
+                            columns.join(", ")
+                        )
+                    })
+            })
+            .collect::<anyhow::Result<Vec<usize>>>()?;
+
+        Ok(Printer {
+            writer,
+            format,
+            numeric: vec![true; selected.len()],
+            columns: selected,
+            indexes,
+            count: 0,
+            sample: Vec::new(),
+            widths: None,
+        })
+    }
+
+    /// Prints a row, given with all the columns, and the path of its file if it has one.
+    pub fn row(&mut self, row: &[JsonValue], path: Option<&Path>) -> anyhow::Result<()> {
+        let values: Vec<&JsonValue> = self.indexes.iter().map(|&index| &row[index]).collect();
+        self.count += 1;
+
+        match self.format {
+            OutputFormat::Table => {
+                for (numeric, value) in self.numeric.iter_mut().zip(&values) {
+                    *numeric &= value.is_number() || value.is_null();
+                }
+                let cells: Vec<String> = values.iter().map(|value| text(value, "-")).collect();
+                if self.widths.is_some() {
+                    self.write_line(&cells)?;
+                } else {
+                    self.sample.push(cells);
+                    if self.sample.len() == TABLE_SAMPLE {
+                        self.write_sample()?;
+                    }
+                }
+            }
+            OutputFormat::Json | OutputFormat::Jsonl => {
+                let object = self.object(&values);
+                match self.format {
+                    OutputFormat::Jsonl => writeln!(self.writer, "{}", object)?,
+                    _ if self.count == 1 => write!(self.writer, "[{}", object)?,
+                    _ => write!(self.writer, ",{}", object)?,
+                }
+            }
+            OutputFormat::Csv => {
+                if self.count == 1 {
+                    let header = csv_line(self.columns.iter().map(String::as_str))?;
+                    self.writer.write_all(&header)?;
+                }
+                let line = csv_line(values.iter().map(|value| text(value, "")))?;
+                self.writer.write_all(&line)?;
+            }
+            OutputFormat::Paths | OutputFormat::Paths0 => {
+                let path = path.ok_or_else(|| {
+                    anyhow::anyhow!("This listing has no files, use another format")
+                })?;
+                let separator: &[u8] = if self.format == OutputFormat::Paths0 {
+                    b"\0"
+                } else {
+                    b"\n"
+                };
+                self.writer.write_all(path.as_os_str().as_encoded_bytes())?;
+                self.writer.write_all(separator)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints what's left: the end of the JSON array, or the table if it's small.
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        match self.format {
+            OutputFormat::Table if self.widths.is_none() => self.write_sample()?,
+            OutputFormat::Json if self.count == 0 => writeln!(self.writer, "[]")?,
+            OutputFormat::Json => writeln!(self.writer, "]")?,
+            OutputFormat::Csv if self.count == 0 => {
+                let header = csv_line(self.columns.iter().map(String::as_str))?;
+                self.writer.write_all(&header)?;
+            }
+            _ => (),
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    // A row as a JSON object, its keys in the order of the columns.
+    fn object(&self, values: &[&JsonValue]) -> String {
+        let members: Vec<String> = self
+            .columns
+            .iter()
+            .zip(values)
+            .map(|(column, value)| format!("{}:{}", JsonValue::from(column.as_str()), value))
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    // Sizes the columns of the table from the rows read so far, and prints them.
+    fn write_sample(&mut self) -> anyhow::Result<()> {
+        let headers: Vec<String> = self.columns.iter().map(|c| c.to_uppercase()).collect();
+        let widths = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                self.sample
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .chain([header.len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        self.widths = Some(widths);
+        // the numbers are aligned on the right, when the whole column is made of them:
+        if self.sample.is_empty() {
+            self.numeric = vec![false; self.columns.len()];
+        }
+
+        self.write_line(&headers)?;
+        for row in std::mem::take(&mut self.sample) {
+            self.write_line(&row)?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&mut self, cells: &[String]) -> anyhow::Result<()> {
+        let widths = self.widths.as_deref().unwrap_or_default();
+        let padded: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if self.numeric[i] {
+                    format!("{:>width$}", cell, width = widths[i])
+                } else {
+                    format!("{:<width$}", cell, width = widths[i])
+                }
+            })
+            .collect();
+        writeln!(self.writer, "{}", padded.join("  ").trim_end())?;
+        Ok(())
+    }
+}
+
+// A row of CSV, with its line end.
+fn csv_line<I, T>(values: I) -> anyhow::Result<Vec<u8>>
+where
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(values)?;
+    Ok(writer.into_inner().map_err(|err| err.into_error())?)
+}
+
+/// A value as text, like in the table and CSV formats: the lists are joined by commas.
+pub fn text(value: &JsonValue, null: &str) -> String {
+    match value {
+        JsonValue::Null => null.to_string(),
+        JsonValue::String(text) => text.clone(),
+        JsonValue::Array(values) => values
+            .iter()
+            .map(|value| text(value, null))
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn output(format: OutputFormat, selected: &[&str]) -> anyhow::Result<String> {
+        let rows = [
+            (
+                vec![json!(2), json!("2023-05-04/a b.jpg"), json!("Ricoh")],
+                PathBuf::from("2023-05-04/a b.jpg"),
+            ),
+            (
+                vec![json!(10), json!("2023-05-05/c.jpg"), JsonValue::Null],
+                PathBuf::from("2023-05-05/c.jpg"),
+            ),
+        ];
+        let selected: Vec<String> = selected.iter().map(|name| name.to_string()).collect();
+
+        let mut data = Vec::new();
+        let mut printer = Printer::new(&mut data, format, &["id", "path", "make"], &selected)?;
+        for (row, path) in &rows {
+            printer.row(row, Some(path))?;
+        }
+        printer.finish()?;
+        Ok(String::from_utf8(data)?)
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            output(OutputFormat::Table, &[]).unwrap(),
+            "ID  PATH                MAKE\n \
+             2  2023-05-04/a b.jpg  Ricoh\n\
+             10  2023-05-05/c.jpg    -\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Json, &["make", "id"]).unwrap(),
+            "[{\"make\":\"Ricoh\",\"id\":2},{\"make\":null,\"id\":10}]\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Jsonl, &[]).unwrap(),
+            "{\"id\":2,\"path\":\"2023-05-04/a b.jpg\",\"make\":\"Ricoh\"}\n\
+             {\"id\":10,\"path\":\"2023-05-05/c.jpg\",\"make\":null}\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Csv, &[]).unwrap(),
+            "id,path,make\n2,2023-05-04/a b.jpg,Ricoh\n10,2023-05-05/c.jpg,\n"
+        );
+        assert_eq!(
+            output(OutputFormat::Paths0, &[]).unwrap(),
+            "2023-05-04/a b.jpg\u{0}2023-05-05/c.jpg\u{0}"
+        );
+        assert!(output(OutputFormat::Csv, &["colour"]).is_err());
+    }
+
+    #[test]
+    fn test_empty() {
+        let finish = |format| {
+            let mut data = Vec::new();
+            Printer::new(&mut data, format, &["count"], &[])
+                .unwrap()
+                .finish()
+                .unwrap();
+            String::from_utf8(data).unwrap()
+        };
+        assert_eq!(finish(OutputFormat::Json), "[]\n");
+        assert_eq!(finish(OutputFormat::Table), "COUNT\n");
+        assert_eq!(finish(OutputFormat::Paths), "");
+    }
+}
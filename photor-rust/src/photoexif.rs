@@ -1,3 +1,8 @@
+use crate::annotations;
+use crate::exposure;
+use crate::gps;
+use crate::models::NewVideo;
+use crate::video;
 use lazy_static::lazy_static;
 use regex::Regex;
 use serde::de::{self, Deserializer};
@@ -6,7 +11,8 @@ use serde_json::Value as JsonValue;
 use std::path::Path;
 use std::process::Command;
 
-fn deserialize_shutter_speed<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+// Deserializes a value that exiftool outputs either as a string or as a number.
+fn deserialize_string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -20,6 +26,29 @@ where
     }
 }
 
+// Deserializes a list that exiftool outputs as an array when it has several items, or as a
+// single string or number otherwise.
+fn deserialize_string_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    let item = |value: JsonValue| match value {
+        JsonValue::String(s) => Ok(Some(s)),
+        JsonValue::Number(n) => Ok(Some(n.to_string())),
+        JsonValue::Null => Ok(None),
+
+        _ => Err(de::Error::custom("Expected a string or a number")),
+    };
+    match value {
+        JsonValue::Array(values) => values
+            .into_iter()
+            .filter_map(|value| item(value).transpose())
+            .collect(),
+        value => Ok(item(value)?.into_iter().collect()),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PExif {
     // 2 dates are fetched from the metadata: the OriginaleDateTime, and the CreateDate.
@@ -55,15 +84,38 @@ pub struct PExif {
     // Most of the time and for sub second shutter speeds, the value comes as a String like
     // `"1/100"`. Sometimes though, they come as a float, like `0.3`
     // Also `default` is added so that in case of a missing "ShutterSpeedValue" key on the JSON,
-    // None is returned. It is needed here since deserialize_shutter_speed doesn't manage this
+    // None is returned. It is needed here since deserialize_string_or_number doesn't manage this
     // case.
     #[serde(
         rename = "ShutterSpeedValue",
         default,
-        deserialize_with = "deserialize_shutter_speed"
+        deserialize_with = "deserialize_string_or_number"
     )]
     pub shutter_speed: Option<String>,
 
+    // The exposure time actually used, and the f-number, more accurate than the APEX values above
+    // (ShutterSpeedValue, ApertureValue).
+    #[serde(
+        rename = "ExposureTime",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub exposure_time: Option<String>,
+
+    #[serde(
+        rename = "FNumber",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub f_number: Option<String>,
+
+    #[serde(
+        rename = "FocalLengthIn35mmFormat",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub focal_length_35mm: Option<String>,
+
     // ------------------------------
     // Camera:
     #[serde(rename = "Make")]
@@ -82,6 +134,347 @@ pub struct PExif {
 
     #[serde(rename = "LensModel")]
     pub lens_model: Option<String>,
+
+    // ------------------------------
+    // Annotations (EXIF, XMP and IPTC), written by the cameras, phones or editing tools:
+    #[serde(
+        rename = "Orientation",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub orientation: Option<String>,
+
+    #[serde(
+        rename = "Rating",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub rating: Option<String>,
+
+    #[serde(rename = "Label")]
+    pub label: Option<String>,
+
+    #[serde(
+        rename = "Subject",
+        default,
+        deserialize_with = "deserialize_string_list"
+    )]
+    pub subject: Vec<String>,
+
+    #[serde(
+        rename = "Keywords",
+        default,
+        deserialize_with = "deserialize_string_list"
+    )]
+    pub keywords: Vec<String>,
+
+    // the captions, in priority order: XMP, IPTC, then EXIF.
+    #[serde(
+        rename = "Description",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub description: Option<String>,
+
+    #[serde(
+        rename = "Caption-Abstract",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub caption_abstract: Option<String>,
+
+    #[serde(
+        rename = "ImageDescription",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub image_description: Option<String>,
+
+    // ------------------------------
+    // Serial numbers and shutter count (in the maker notes, not written by all the vendors):
+    #[serde(
+        rename = "SerialNumber",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub serial_number: Option<String>,
+
+    #[serde(
+        rename = "InternalSerialNumber",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub internal_serial_number: Option<String>,
+
+    #[serde(
+        rename = "LensSerialNumber",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub lens_serial_number: Option<String>,
+
+    #[serde(
+        rename = "ShutterCount",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub shutter_count: Option<String>,
+
+    // used instead of the shutter count by some vendors (Fujifilm, Olympus...):
+    #[serde(
+        rename = "ImageCount",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub image_count: Option<String>,
+
+    // ------------------------------
+    // Location:
+    // The coordinates come like `38 deg 42' 50.04" N`, the hemisphere being either in the value
+    // (composite tags) or in the reference tags. They're converted to signed decimal degrees by
+    // the methods below.
+    #[serde(
+        rename = "GPSLatitude",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub gps_latitude: Option<String>,
+
+    #[serde(rename = "GPSLatitudeRef")]
+    pub gps_latitude_ref: Option<String>,
+
+    #[serde(
+        rename = "GPSLongitude",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub gps_longitude: Option<String>,
+
+    #[serde(rename = "GPSLongitudeRef")]
+    pub gps_longitude_ref: Option<String>,
+
+    #[serde(
+        rename = "GPSAltitude",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub gps_altitude: Option<String>,
+
+    #[serde(
+        rename = "GPSAltitudeRef",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub gps_altitude_ref: Option<String>,
+
+    #[serde(
+        rename = "GPSImgDirection",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub gps_img_direction: Option<String>,
+
+    // ------------------------------
+    // Video:
+    // The duration comes like `12.34 s` or `0:01:23`, the bitrate like `12.3 Mbps`.
+    #[serde(
+        rename = "Duration",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub duration: Option<String>,
+
+    #[serde(rename = "CompressorID")]
+    pub compressor_id: Option<String>,
+
+    #[serde(rename = "AudioFormat")]
+    pub audio_format: Option<String>,
+
+    #[serde(
+        rename = "VideoFrameRate",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub video_frame_rate: Option<String>,
+
+    #[serde(
+        rename = "AvgBitrate",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub avg_bitrate: Option<String>,
+
+    #[serde(
+        rename = "Rotation",
+        default,
+        deserialize_with = "deserialize_string_or_number"
+    )]
+    pub rotation: Option<String>,
+
+    // ------------------------------
+    // The complete exiftool output, stored as is in the database:
+    #[serde(skip)]
+    pub metadata: JsonValue,
+}
+
+impl PExif {
+    pub fn latitude(&self) -> Option<f64> {
+        gps::parse_coordinate(
+            self.gps_latitude.as_deref()?,
+            self.gps_latitude_ref.as_deref(),
+        )
+    }
+
+    pub fn longitude(&self) -> Option<f64> {
+        gps::parse_coordinate(
+            self.gps_longitude.as_deref()?,
+            self.gps_longitude_ref.as_deref(),
+        )
+    }
+
+    pub fn altitude(&self) -> Option<f64> {
+        gps::parse_altitude(
+            self.gps_altitude.as_deref()?,
+            self.gps_altitude_ref.as_deref(),
+        )
+    }
+
+    pub fn gps_direction(&self) -> Option<f64> {
+        gps::parse_direction(self.gps_img_direction.as_deref()?)
+    }
+
+    /// The exposure time in seconds, from ExposureTime or else from the shutter speed.
+    pub fn exposure_seconds(&self) -> Option<f64> {
+        self.exposure_time
+            .as_deref()
+            .and_then(exposure::parse_exposure_time)
+            .or_else(|| {
+                self.shutter_speed
+                    .as_deref()
+                    .and_then(exposure::parse_exposure_time)
+            })
+    }
+
+    pub fn f_number(&self) -> Option<f64> {
+        self.f_number
+            .as_deref()
+            .and_then(|f_number| f_number.trim().parse::<f64>().ok())
+            .filter(|f_number| *f_number > 0.0)
+    }
+
+    pub fn focal_length_mm(&self) -> Option<f64> {
+        exposure::parse_focal_length(self.focal_length.as_deref()?)
+    }
+
+    pub fn focal_length_35mm(&self) -> Option<f64> {
+        exposure::parse_focal_length(self.focal_length_35mm.as_deref()?)
+    }
+
+    pub fn camera_serial(&self) -> Option<String> {
+        serial(self.serial_number.as_deref()).or(serial(self.internal_serial_number.as_deref()))
+    }
+
+    pub fn lens_serial(&self) -> Option<String> {
+        serial(self.lens_serial_number.as_deref())
+    }
+
+    pub fn shutter_count(&self) -> Option<i64> {
+        let count = |value: Option<&str>| {
+            value
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .filter(|count| *count > 0)
+        };
+        count(self.shutter_count.as_deref()).or(count(self.image_count.as_deref()))
+    }
+
+    pub fn orientation(&self) -> Option<i64> {
+        annotations::parse_orientation(self.orientation.as_deref()?)
+    }
+
+    /// The width and height of the photo as displayed, with the orientation applied.
+    pub fn display_dimensions(&self) -> (Option<i64>, Option<i64>) {
+        annotations::display_dimensions(
+            self.image_width.map(i64::from),
+            self.image_height.map(i64::from),
+            self.orientation(),
+        )
+    }
+
+    pub fn rating(&self) -> Option<i64> {
+        annotations::parse_rating(self.rating.as_deref()?)
+    }
+
+    pub fn color_label(&self) -> Option<String> {
+        self.label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(str::to_string)
+    }
+
+    pub fn caption(&self) -> Option<String> {
+        [
+            &self.description,
+            &self.caption_abstract,
+            &self.image_description,
+        ]
+        .into_iter()
+        .find_map(|caption| annotations::clean_caption(caption.as_deref()?))
+    }
+
+    /// The XMP subject and IPTC keywords, merged.
+    pub fn tags(&self) -> Vec<String> {
+        annotations::keywords_to_tags(self.subject.iter().chain(&self.keywords))
+    }
+
+    pub fn is_video(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|mime_type| mime_type.starts_with("video/"))
+    }
+
+    /// The video specific metadata, None if the file isn't a video.
+    pub fn video(&self) -> Option<NewVideo> {
+        if !self.is_video() {
+            return None;
+        }
+
+        let rotation = self
+            .rotation
+            .as_deref()
+            .and_then(|rotation| rotation.trim().parse::<f64>().ok())
+            .map(|rotation| rotation as i64);
+        let (display_width, display_height) = video::display_dimensions(
+            self.image_width.map(i64::from),
+            self.image_height.map(i64::from),
+            rotation,
+        );
+
+        Some(NewVideo {
+            duration_seconds: self
+                .duration
+                .as_deref()
+                .and_then(video::parse_duration_seconds),
+            video_codec: self.compressor_id.clone(),
+            audio_codec: self.audio_format.clone(),
+            frame_rate: self
+                .video_frame_rate
+                .as_deref()
+                .and_then(|rate| rate.trim().parse().ok()),
+            bitrate: self.avg_bitrate.as_deref().and_then(video::parse_bitrate),
+            rotation,
+            display_width,
+            display_height,
+        })
+    }
+}
+
+// Some bodies write placeholder serial numbers, like `0000000` or blanks.
+fn serial(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|serial| serial.chars().any(|c| c.is_alphanumeric() && c != '0'))
+        .map(str::to_string)
 }
 
 pub fn read(photo_path: &Path) -> Result<PExif, String> {
@@ -89,7 +482,7 @@ pub fn read(photo_path: &Path) -> Result<PExif, String> {
         .args(["-json", "-d", "%Y-%m-%d %H:%M:%S"])
         .arg(photo_path)
         .output()
-        .map_err(|_err| format!("failed to execute process"))?;
+        .map_err(|_err| "failed to execute process".to_string())?;
 
     parse_json(output.stdout)
         .map_err(|err| format!("Failed to parse the JSON to a PExif: {:?}", err))
@@ -97,11 +490,21 @@ pub fn read(photo_path: &Path) -> Result<PExif, String> {
 
 fn parse_json(data: Vec<u8>) -> Result<PExif, String> {
     // exiftool returns an array containing one object (the EXIF), hence the Vec here.
-    let mut datas: Vec<PExif> = serde_json::from_slice(&data).map_err(|err| err.to_string())?;
+    let mut datas: Vec<JsonValue> = serde_json::from_slice(&data).map_err(|err| err.to_string())?;
 
-    // pop is used to remove the value from the Vec, so that the PExif *value* can be returned,
+    // pop is used to remove the value from the Vec, so that the object *value* can be returned,
     // without problems of lifetimes.
-    datas.pop().ok_or("No EXIF data found?".to_string())
+    let mut metadata = datas.pop().ok_or("No EXIF data found?".to_string())?;
+    let mut pexif: PExif =
+        serde_json::from_value(metadata.clone()).map_err(|err| err.to_string())?;
+
+    // the path of the file read (possibly a temporary one) isn't metadata of the photo:
+    if let Some(object) = metadata.as_object_mut() {
+        object.remove("SourceFile");
+    }
+    pexif.metadata = metadata;
+
+    Ok(pexif)
 }
 
 fn is_some_good_date(opt: &Option<String>) -> bool {
@@ -113,19 +516,12 @@ fn is_some_good_date(opt: &Option<String>) -> bool {
     opt.is_some() && RE.is_match(opt.as_ref().unwrap().as_str())
 }
 
-/// we look for a date we can use (defined and with the right format). We start by checking the
-/// OriginaleDateTime, and then CreateDate.
-pub fn find_usable_date(
-    date_time_original: Option<String>,
-    create_date: Option<String>,
-) -> Option<String> {
-    if is_some_good_date(&date_time_original) {
-        return date_time_original;
-    }
-
-    if is_some_good_date(&create_date) {
-        return create_date;
-    }
-
-    None
+/// we look for a date we can use (defined and with the right format), among the candidates given
+/// in priority order. For the EXIF, we start by checking the OriginaleDateTime, and then
+/// CreateDate. Other sources (like the sidecars of Google Takeout) come after.
+pub fn find_usable_date<I>(candidates: I) -> Option<String>
+where
+    I: IntoIterator<Item = Option<String>>,
+{
+    candidates.into_iter().find(is_some_good_date).flatten()
 }
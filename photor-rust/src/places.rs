@@ -0,0 +1,69 @@
+use crate::gps::Point;
+use isocountry::CountryCode;
+use lazy_static::lazy_static;
+use reverse_geocoder::ReverseGeocoder;
+
+// Beyond this distance from the nearest known city (out at sea, in the wilderness...), no place
+// is given to a location.
+const MAX_DISTANCE_METERS: f64 = 100_000.0;
+
+lazy_static! {
+    // The GeoNames extract (cities of more than 1000 inhabitants) bundled in the binary by the
+    // reverse_geocoder crate. No online service is used. It's loaded on first use only, as it
+    // takes a moment.
+    static ref GEOCODER: ReverseGeocoder = ReverseGeocoder::new();
+}
+
+/// The place names of a location.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Place {
+    /// ISO 3166-1 alpha-2 code, like `PT`.
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+/// Finds the place of a location: the nearest city of the dataset, with its region and country.
+pub fn locate(latitude: f64, longitude: f64) -> Place {
+    let record = GEOCODER.search((latitude, longitude)).record;
+
+    let distance = Point {
+        latitude,
+        longitude,
+    }
+    .distance_meters(&Point {
+        latitude: record.lat,
+        longitude: record.lon,
+    });
+    if distance > MAX_DISTANCE_METERS {
+        return Place::default();
+    }
+
+    let non_empty = |value: &str| Some(value.to_string()).filter(|value| !value.is_empty());
+
+    Place {
+        country_code: non_empty(&record.cc),
+        country: CountryCode::for_alpha2(&record.cc)
+            .map(|country| country.name().to_string())
+            .ok(),
+        region: non_empty(&record.admin1),
+        city: non_empty(&record.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let place = locate(41.1496, -8.6110);
+        assert_eq!(place.country_code.as_deref(), Some("PT"));
+        assert_eq!(place.country.as_deref(), Some("Portugal"));
+        assert_eq!(place.city.as_deref(), Some("Porto"));
+
+        // the middle of the Atlantic:
+        assert_eq!(locate(30.0, -40.0), Place::default());
+    }
+}
@@ -0,0 +1,602 @@
+use crate::units;
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A selection of photos, parsed from a query like
+/// `make:ricoh iso>=3200 date:2023-05..2023-06 type:video -tag:private`.
+///
+/// The terms are ANDed, unless separated by `OR`; a `-` negates a term or a group in
+/// parentheses, and `@name` refers to a saved query.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// The empty query, selecting all the photos.
+    All,
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Condition(Condition),
+    /// A saved query, replaced by its own query by `expand`.
+    Saved(String),
+}
+
+impl Query {
+    /// Whether one of the conditions of the query is an `import:` one. The saved queries are
+    /// expected to be expanded.
+    pub fn selects_imports(&self) -> bool {
+        match self {
+            Query::And(queries) | Query::Or(queries) => queries.iter().any(Query::selects_imports),
+            Query::Not(query) => query.selects_imports(),
+            Query::Condition(condition) => matches!(condition, Condition::Import(_)),
+            Query::All | Query::Saved(_) => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    Compare {
+        field: NumberField,
+        op: Operator,
+        value: Value,
+    },
+    /// A part of the raw or normalized name of the camera make, model or lens, ignoring the case.
+    Gear {
+        kind: GearField,
+        name: String,
+    },
+    /// A tag, ignoring the case.
+    Tag(String),
+    Type(MediaType),
+    /// A MIME type, or a pattern like `image/*`.
+    Mime(String),
+    /// A city, region or country name, or a country code, ignoring the case.
+    Place(String),
+    /// The beginning of the directory, like `2023-05`.
+    Directory(String),
+    /// A part of the file name, ignoring the case.
+    Filename(String),
+    /// The beginning of the partial hash.
+    Hash(String),
+    /// The photos of an archive, or of any archive.
+    Archive(Option<String>),
+    /// The photos of an import of the web app, or of the last one.
+    Import(Option<i64>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberField {
+    /// The day the photo was taken.
+    Date,
+    Iso,
+    /// The f-number.
+    Aperture,
+    /// The focal length in millimeters.
+    Focal,
+    Focal35mm,
+    /// The size of the file in bytes.
+    Size,
+    Rating,
+    /// The duration of the videos in seconds.
+    Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GearField {
+    Make,
+    Model,
+    Lens,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediaType {
+    Photo,
+    Video,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operator {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Operator {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Operator::Equal => "=",
+            Operator::Less => "<",
+            Operator::LessOrEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Date(NaiveDate),
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Not,
+    Or,
+    Term(String),
+}
+
+/// Parses a query. The empty query selects all the photos.
+pub fn parse(text: &str) -> Result<Query, String> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    if parser.tokens.is_empty() {
+        return Ok(Query::All);
+    }
+
+    // the terms are all read, up to a parenthesis without its opening one:
+    let query = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(_) => Err("unexpected )".to_string()),
+    }
+}
+
+/// Replaces the references to saved queries (`@name`) with their queries, given by name.
+pub fn expand(query: &Query, saved: &HashMap<String, String>) -> Result<Query, String> {
+    expand_with(query, saved, &mut Vec::new())
+}
+
+// `expanding`: the saved queries being expanded, to detect the ones referring to themselves.
+fn expand_with(
+    query: &Query,
+    saved: &HashMap<String, String>,
+    expanding: &mut Vec<String>,
+) -> Result<Query, String> {
+    let expand_all = |queries: &[Query], expanding: &mut Vec<String>| {
+        queries
+            .iter()
+            .map(|query| expand_with(query, saved, expanding))
+            .collect::<Result<Vec<Query>, String>>()
+    };
+
+    Ok(match query {
+        Query::And(queries) => Query::And(expand_all(queries, expanding)?),
+        Query::Or(queries) => Query::Or(expand_all(queries, expanding)?),
+        Query::Not(query) => Query::Not(Box::new(expand_with(query, saved, expanding)?)),
+        Query::Saved(name) => {
+            if expanding.contains(name) {
+                return Err(format!("the saved query @{} refers to itself", name));
+            }
+            let text = saved
+                .get(name)
+                .ok_or_else(|| format!("no saved query named @{}", name))?;
+            let parsed = parse(text).map_err(|err| format!("in @{}: {}", name, err))?;
+
+            expanding.push(name.clone());
+            let expanded = expand_with(&parsed, saved, expanding)?;
+            expanding.pop();
+            expanded
+        }
+        query => query.clone(),
+    })
+}
+
+/// Checks the name of a saved query: letters, digits, `_` and `-`.
+pub fn check_name(name: &str) -> Result<(), String> {
+    lazy_static! {
+        static ref NAME_RE: Regex = Regex::new(r"^[A-Za-z0-9_-]+$").unwrap();
+    }
+    if NAME_RE.is_match(name) {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid name {}, expected letters, digits, _ or -",
+            name
+        ))
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                // a term, up to a space or a parenthesis outside of quotes (which are removed):
+                let mut term = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    }
+                    chars.next();
+                    if c == '"' {
+                        quoted = !quoted;
+                    } else {
+                        term.push(c);
+                    }
+                }
+                if quoted {
+                    return Err(format!("missing closing quote in {}", term));
+                }
+                tokens.push(if term == "OR" {
+                    Token::Or
+                } else {
+                    Token::Term(term)
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, String> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            queries.push(self.parse_and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.remove(0)
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Query, String> {
+        let mut queries = Vec::new();
+        while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::Close)) {
+            queries.push(self.parse_unary()?);
+        }
+        Ok(match queries.len() {
+            0 => return Err("expected a term".to_string()),
+            1 => queries.remove(0),
+            _ => Query::And(queries),
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, String> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err("missing )".to_string()),
+                }
+            }
+            Some(Token::Term(term)) => {
+                let term = term.clone();
+                parse_term(&term)
+            }
+            _ => Err("expected a term".to_string()),
+        }
+    }
+}
+
+// A term: FIELD:VALUE, FIELD>=VALUE (or another comparison), or @NAME.
+fn parse_term(term: &str) -> Result<Query, String> {
+    lazy_static! {
+        static ref TERM_RE: Regex = Regex::new(r"^([a-z0-9_]+)(:|>=|<=|>|<|=)(.+)$").unwrap();
+    }
+
+    if let Some(name) = term.strip_prefix('@') {
+        check_name(name)?;
+        return Ok(Query::Saved(name.to_string()));
+    }
+    let captures = TERM_RE
+        .captures(term)
+        .ok_or_else(|| format!("invalid term {}, expected FIELD:VALUE", term))?;
+    let (field, operator, value) = (&captures[1], &captures[2], captures[3].trim());
+
+    let number_field = match field {
+        "date" => Some(NumberField::Date),
+        "iso" => Some(NumberField::Iso),
+        "aperture" | "f" => Some(NumberField::Aperture),
+        "focal" => Some(NumberField::Focal),
+        "focal35" => Some(NumberField::Focal35mm),
+        "size" => Some(NumberField::Size),
+        "rating" => Some(NumberField::Rating),
+        "duration" => Some(NumberField::Duration),
+        _ => None,
+    };
+    if let Some(field) = number_field {
+        return parse_comparison(field, operator, value);
+    }
+
+    if operator != ":" && operator != "=" {
+        return Err(format!("{} can't be compared with {}", field, operator));
+    }
+    let text = value.to_string();
+    let condition = match field {
+        "make" => Condition::Gear {
+            kind: GearField::Make,
+            name: text,
+        },
+        "model" => Condition::Gear {
+            kind: GearField::Model,
+            name: text,
+        },
+        "lens" => Condition::Gear {
+            kind: GearField::Lens,
+            name: text,
+        },
+        "tag" => Condition::Tag(text),
+        "type" => match value {
+            "photo" => Condition::Type(MediaType::Photo),
+            "video" => Condition::Type(MediaType::Video),
+            _ => return Err(format!("invalid type {}, expected photo or video", value)),
+        },
+        "mime" => Condition::Mime(text),
+        "place" => Condition::Place(text),
+        "dir" => Condition::Directory(text),
+        "name" => Condition::Filename(text),
+        "hash" => Condition::Hash(text.to_lowercase()),
+        "archive" if value == "any" => Condition::Archive(None),
+        "archive" => Condition::Archive(Some(text)),
+        "import" if value == "last" => Condition::Import(None),
+        "import" => {
+            Condition::Import(Some(value.parse().map_err(|_| {
+                format!("invalid import {}, expected an id or \"last\"", value)
+            })?))
+        }
+        _ => return Err(format!("unknown field {}", field)),
+    };
+    Ok(Query::Condition(condition))
+}
+
+// A comparison, or a range (FIELD:MIN..MAX) made of two. The dates can be partial (2023 or
+// 2023-05), standing for all their days.
+fn parse_comparison(field: NumberField, operator: &str, value: &str) -> Result<Query, String> {
+    let compare = |op, value| Query::Condition(Condition::Compare { field, op, value });
+    let bounds = |value: &str| -> Result<(Value, Value), String> {
+        if field == NumberField::Date {
+            let (first, last) = parse_partial_date(value)?;
+            Ok((Value::Date(first), Value::Date(last)))
+        } else {
+            let number = Value::Number(parse_number(field, value)?);
+            Ok((number, number))
+        }
+    };
+
+    if operator == ":" {
+        if let Some((min, max)) = value.split_once("..") {
+            let mut queries = Vec::new();
+            if !min.is_empty() {
+                queries.push(compare(Operator::GreaterOrEqual, bounds(min)?.0));
+            }
+            if !max.is_empty() {
+                queries.push(compare(Operator::LessOrEqual, bounds(max)?.1));
+            }
+            return match queries.len() {
+                0 => Err(format!("invalid range {}", value)),
+                1 => Ok(queries.remove(0)),
+                _ => Ok(Query::And(queries)),
+            };
+        }
+    }
+
+    let (first, last) = bounds(value)?;
+    Ok(match operator {
+        ":" | "=" if first == last => compare(Operator::Equal, first),
+        ":" | "=" => Query::And(vec![
+            compare(Operator::GreaterOrEqual, first),
+            compare(Operator::LessOrEqual, last),
+        ]),
+        "<" => compare(Operator::Less, first),
+        "<=" => compare(Operator::LessOrEqual, last),
+        ">" => compare(Operator::Greater, last),
+        _ => compare(Operator::GreaterOrEqual, first),
+    })
+}
+
+fn parse_number(field: NumberField, value: &str) -> Result<f64, String> {
+    match field {
+        NumberField::Size => units::parse_size(value),
+        NumberField::Duration => {
+            units::parse_duration(value).map(|duration| duration.num_milliseconds() as f64 / 1000.0)
+        }
+        NumberField::Aperture => value
+            .trim_start_matches("f/")
+            .parse()
+            .map_err(|_| format!("invalid f-number {}", value)),
+        _ => value
+            .parse()
+            .map_err(|_| format!("invalid number {}", value)),
+    }
+}
+
+// The first and last days of a year (2023), a month (2023-05) or a day.
+fn parse_partial_date(value: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let invalid = || {
+        format!(
+            "invalid date {}, expected something like 2023, 2023-05 or 2023-05-04",
+            value
+        )
+    };
+    let parts = value
+        .split('-')
+        .map(|part| part.parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| invalid())?;
+
+    let (first, last) = match parts.as_slice() {
+        [year] => (
+            NaiveDate::from_ymd_opt(*year as i32, 1, 1),
+            NaiveDate::from_ymd_opt(*year as i32, 12, 31),
+        ),
+        [year, month] => {
+            let first = NaiveDate::from_ymd_opt(*year as i32, *month, 1);
+            let next = match month {
+                12 => NaiveDate::from_ymd_opt(*year as i32 + 1, 1, 1),
+                month => NaiveDate::from_ymd_opt(*year as i32, month + 1, 1),
+            };
+            (first, next.and_then(|next| next.pred_opt()))
+        }
+        [year, month, day] => {
+            let day = NaiveDate::from_ymd_opt(*year as i32, *month, *day);
+            (day, day)
+        }
+        _ => (None, None),
+    };
+    match (first, last) {
+        (Some(first), Some(last)) => Ok((first, last)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> Value {
+        Value::Date(NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap())
+    }
+
+    fn compare(field: NumberField, op: Operator, value: Value) -> Query {
+        Query::Condition(Condition::Compare { field, op, value })
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("make:ricoh iso>=3200 date:2023-05..2023-06 type:video -tag:private"),
+            Ok(Query::And(vec![
+                Query::Condition(Condition::Gear {
+                    kind: GearField::Make,
+                    name: "ricoh".to_string()
+                }),
+                compare(
+                    NumberField::Iso,
+                    Operator::GreaterOrEqual,
+                    Value::Number(3200.0)
+                ),
+                Query::And(vec![
+                    compare(
+                        NumberField::Date,
+                        Operator::GreaterOrEqual,
+                        date("2023-05-01")
+                    ),
+                    compare(NumberField::Date, Operator::LessOrEqual, date("2023-06-30")),
+                ]),
+                Query::Condition(Condition::Type(MediaType::Video)),
+                Query::Not(Box::new(Query::Condition(Condition::Tag(
+                    "private".to_string()
+                )))),
+            ]))
+        );
+
+        assert_eq!(
+            parse(r#"(tag:"new york" OR place:paris) -(@family)"#),
+            Ok(Query::And(vec![
+                Query::Or(vec![
+                    Query::Condition(Condition::Tag("new york".to_string())),
+                    Query::Condition(Condition::Place("paris".to_string())),
+                ]),
+                Query::Not(Box::new(Query::Saved("family".to_string()))),
+            ]))
+        );
+
+        assert_eq!(parse("  "), Ok(Query::All));
+        assert_eq!(
+            parse("date>2023"),
+            Ok(compare(
+                NumberField::Date,
+                Operator::Greater,
+                date("2023-12-31")
+            ))
+        );
+        assert_eq!(
+            parse("size:..2MB"),
+            Ok(compare(
+                NumberField::Size,
+                Operator::LessOrEqual,
+                Value::Number(2097152.0)
+            ))
+        );
+        assert!(parse("colour:red").is_err());
+        assert!(parse("make>ricoh").is_err());
+        assert!(parse("(iso:100").is_err());
+        assert!(parse("date:2023-13").is_err());
+        assert!(parse("tag:\"new york").is_err());
+    }
+
+    #[test]
+    fn test_expand() {
+        let saved: HashMap<String, String> = [
+            ("night", "iso>=3200"),
+            ("best", "rating>=4 @night"),
+            ("loop", "@loop"),
+        ]
+        .iter()
+        .map(|(name, query)| (name.to_string(), query.to_string()))
+        .collect();
+
+        assert_eq!(
+            expand(&parse("-@best").unwrap(), &saved),
+            Ok(Query::Not(Box::new(Query::And(vec![
+                compare(
+                    NumberField::Rating,
+                    Operator::GreaterOrEqual,
+                    Value::Number(4.0)
+                ),
+                compare(
+                    NumberField::Iso,
+                    Operator::GreaterOrEqual,
+                    Value::Number(3200.0)
+                ),
+            ]))))
+        );
+        assert!(expand(&parse("@loop").unwrap(), &saved).is_err());
+        assert!(expand(&parse("@unknown").unwrap(), &saved).is_err());
+    }
+
+    #[test]
+    fn test_selects_imports() {
+        assert!(parse("iso>=3200 (tag:new OR -import:last)")
+            .unwrap()
+            .selects_imports());
+        assert!(!parse("iso>=3200 tag:import").unwrap().selects_imports());
+    }
+}
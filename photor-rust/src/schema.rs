@@ -0,0 +1,233 @@
+use crate::models::MigrationRecord;
+use chrono::NaiveDateTime;
+use sqlx::migrate::Migration;
+use std::fmt;
+
+// The migrations of the web app (priv/repo/migrations) this version of photor can work with, when
+// both share the database. They drop the create_day triggers and add the `imports` and
+// `thumbnails` tables.
+pub const KNOWN_WEB_APP_MIGRATIONS: &[i64] = &[
+    20250529000001,
+    20250531000001,
+    20250704000000,
+    20250714212704,
+];
+
+/// The migrations of a database compared to the ones known by this version of photor.
+#[derive(Debug, Default, PartialEq)]
+pub struct VersionCheck {
+    /// Known but not applied yet.
+    pub pending: Vec<i64>,
+    /// Applied but unknown: the database was migrated by a newer version.
+    pub unknown: Vec<i64>,
+}
+
+impl VersionCheck {
+    pub fn new(known: &[i64], applied: &[i64]) -> Self {
+        VersionCheck {
+            pending: difference(known, applied),
+            unknown: difference(applied, known),
+        }
+    }
+
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty() && self.unknown.is_empty()
+    }
+}
+
+/// What the web app added to the database, when it shares it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WebAppSchema {
+    /// The migrations of the web app unknown to this version of photor.
+    pub unknown_migrations: Vec<i64>,
+    /// Whether the `imports` table exists, the photos imported being then linked to an import.
+    pub has_imports: bool,
+    /// Whether the `thumbnails` table exists, listing the thumbnails made of the photos.
+    pub has_thumbnails: bool,
+}
+
+/// The state of a migration, comparing the ones of this version to the ones of the database.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the file changed since (different checksum).
+    Modified,
+    /// Its execution failed, leaving the database in an unknown state.
+    Failed,
+    /// Applied by a newer version of photor.
+    Unknown,
+}
+
+impl fmt::Display for MigrationState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Failed => "failed",
+            MigrationState::Unknown => "unknown",
+        };
+        f.pad(name)
+    }
+}
+
+/// A migration of this version or of the database, as shown by `photor migrate status`.
+#[derive(Debug, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+    /// The checksum of the file, or the one recorded for the unknown migrations.
+    pub checksum: Vec<u8>,
+    pub installed_on: Option<NaiveDateTime>,
+}
+
+impl MigrationStatus {
+    /// What's wrong with the migration applied to the database, if it was modified since or it
+    /// failed: the schema isn't the one expected by this version then.
+    pub fn problem(&self) -> Option<String> {
+        match self.state {
+            MigrationState::Modified => Some(format!(
+                "the migration {} applied to the database was modified",
+                self.version
+            )),
+            MigrationState::Failed => Some(format!(
+                "the migration {} failed, leaving the database in an unknown state",
+                self.version
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Pairs the migrations of this version (the up ones) with the ones applied, sorted by version.
+pub fn migration_statuses(
+    known: &[Migration],
+    applied: &[MigrationRecord],
+) -> Vec<MigrationStatus> {
+    let mut statuses: Vec<MigrationStatus> = known
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| {
+            let record = applied
+                .iter()
+                .find(|record| record.version == migration.version);
+            let state = match record {
+                None => MigrationState::Pending,
+                Some(record) if !record.success => MigrationState::Failed,
+                Some(record) if record.checksum[..] != migration.checksum[..] => {
+                    MigrationState::Modified
+                }
+                Some(_) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+                checksum: migration.checksum.to_vec(),
+                installed_on: record.map(|record| record.installed_on),
+            }
+        })
+        .collect();
+
+    for record in applied {
+        if !statuses
+            .iter()
+            .any(|status| status.version == record.version)
+        {
+            statuses.push(MigrationStatus {
+                version: record.version,
+                description: record.description.clone(),
+                state: MigrationState::Unknown,
+                checksum: record.checksum.clone(),
+                installed_on: Some(record.installed_on),
+            });
+        }
+    }
+
+    statuses.sort_by_key(|status| status.version);
+    statuses
+}
+
+// The versions of `a` not in `b`, sorted.
+fn difference(a: &[i64], b: &[i64]) -> Vec<i64> {
+    let mut versions: Vec<i64> = a
+        .iter()
+        .filter(|version| !b.contains(version))
+        .copied()
+        .collect();
+    versions.sort_unstable();
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::migrate::MigrationType;
+
+    #[test]
+    fn test_version_check() {
+        let check = VersionCheck::new(&[1, 2, 3], &[1, 2, 4]);
+        assert_eq!(check.pending, vec![3]);
+        assert_eq!(check.unknown, vec![4]);
+        assert!(!check.is_up_to_date());
+
+        assert!(VersionCheck::new(&[1, 2], &[2, 1]).is_up_to_date());
+    }
+
+    #[test]
+    fn test_migration_statuses() {
+        let migration =
+            |version, sql| Migration::new(version, "m".into(), MigrationType::ReversibleUp, sql);
+        let known = [
+            migration(1, "create table a (id integer);".into()),
+            Migration::new(
+                1,
+                "m".into(),
+                MigrationType::ReversibleDown,
+                "drop table a;".into(),
+            ),
+            migration(2, "create table b (id integer);".into()),
+            migration(3, "create table c (id integer);".into()),
+        ];
+        let record = |version, checksum: &[u8]| MigrationRecord {
+            version,
+            description: "m".to_string(),
+            installed_on: NaiveDateTime::default(),
+            success: true,
+            checksum: checksum.to_vec(),
+        };
+        let applied = [
+            record(1, &known[0].checksum),
+            record(2, b"changed"),
+            record(4, b"newer"),
+        ];
+
+        let statuses = migration_statuses(&known, &applied);
+        let states: Vec<(i64, MigrationState)> = statuses
+            .iter()
+            .map(|status| (status.version, status.state))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (1, MigrationState::Applied),
+                (2, MigrationState::Modified),
+                (3, MigrationState::Pending),
+                (4, MigrationState::Unknown),
+            ]
+        );
+
+        // the modified migration is the only problem, the pending and unknown ones being handled
+        // by migrating or upgrading:
+        let problems: Vec<String> = statuses
+            .iter()
+            .filter_map(|status| status.problem())
+            .collect();
+        assert_eq!(
+            problems,
+            vec!["the migration 2 applied to the database was modified"]
+        );
+    }
+}
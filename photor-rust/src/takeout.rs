@@ -0,0 +1,242 @@
+use chrono::DateTime;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Google Takeout truncates the sidecar file names (without the `.json` extension) to this length:
+const MAX_SIDECAR_STEM_LENGTH: usize = 46;
+
+#[derive(Debug, Deserialize)]
+struct TakeoutTime {
+    timestamp: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutGeoData {
+    latitude: f64,
+    longitude: f64,
+    altitude: Option<f64>,
+}
+
+impl TakeoutGeoData {
+    // Takeout writes 0.0 coordinates when the location is unknown.
+    fn is_set(&self) -> bool {
+        self.latitude != 0.0 || self.longitude != 0.0
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TakeoutJson {
+    description: Option<String>,
+    photo_taken_time: Option<TakeoutTime>,
+    geo_data: Option<TakeoutGeoData>,
+    geo_data_exif: Option<TakeoutGeoData>,
+}
+
+/// The metadata found in the JSON sidecar of a media file from a Google Takeout (or similar
+/// phone) export.
+#[derive(Debug, Default, PartialEq)]
+pub struct Sidecar {
+    /// The date the photo was taken, formatted like the EXIF dates read by `photoexif` (in UTC
+    /// though, as Takeout only gives a timestamp).
+    pub taken_date: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub altitude: Option<f64>,
+    pub caption: Option<String>,
+}
+
+pub fn parse_sidecar(data: &[u8]) -> Result<Sidecar, String> {
+    let json: TakeoutJson = serde_json::from_slice(data).map_err(|err| err.to_string())?;
+
+    let taken_date = json
+        .photo_taken_time
+        .and_then(|time| time.timestamp.parse::<i64>().ok())
+        .filter(|timestamp| *timestamp > 0)
+        .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string());
+
+    // the location set in Google Photos has the priority over the one read from the EXIF:
+    let geo_data = json
+        .geo_data
+        .filter(TakeoutGeoData::is_set)
+        .or(json.geo_data_exif.filter(TakeoutGeoData::is_set));
+
+    Ok(Sidecar {
+        taken_date,
+        latitude: geo_data.as_ref().map(|geo| geo.latitude),
+        longitude: geo_data.as_ref().map(|geo| geo.longitude),
+        altitude: geo_data.and_then(|geo| geo.altitude),
+        caption: json
+            .description
+            .filter(|description| !description.is_empty()),
+    })
+}
+
+/// Reads the sidecar of the media file, if it can be found next to it.
+pub fn read_sidecar(media_path: &Path) -> Option<Result<Sidecar, String>> {
+    let sidecar_path = find_sidecar(media_path)?;
+
+    Some(
+        fs::read(&sidecar_path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| parse_sidecar(&data))
+            .map_err(|err| format!("Invalid sidecar {}: {}", sidecar_path.display(), err)),
+    )
+}
+
+fn find_sidecar(media_path: &Path) -> Option<PathBuf> {
+    let directory = media_path.parent()?;
+    let media_name = media_path.file_name()?.to_str()?;
+
+    // the exact names are tried first, the directory is listed only if none exists:
+    for name in sidecar_names(media_name) {
+        let path = directory.join(name);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
+    let json_names: Vec<String> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+
+    find_sidecar_name(media_name, json_names.iter().map(String::as_str))
+        .map(|name| directory.join(name))
+}
+
+/// Picks the sidecar of the media file among the given JSON file names (from the same directory).
+pub fn find_sidecar_name<'a, I>(media_name: &str, json_names: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let json_names: Vec<&str> = json_names.into_iter().collect();
+
+    let candidates = sidecar_names(media_name);
+    if let Some(name) = candidates
+        .iter()
+        .find_map(|candidate| json_names.iter().find(|name| *name == candidate))
+    {
+        return Some(name);
+    }
+
+    // Takeout truncates long sidecar names: a JSON file whose name is a truncated candidate is
+    // picked.
+    json_names.into_iter().find(|name| {
+        let stem = name.trim_end_matches(".json");
+        stem.chars().count() == MAX_SIDECAR_STEM_LENGTH
+            && candidates
+                .iter()
+                .any(|candidate| candidate.trim_end_matches(".json").starts_with(stem))
+    })
+}
+
+/// Returns the possible names of the sidecar of a media file, in priority order. They take the
+/// Takeout quirks into account:
+/// - `IMG_1.JPG.json`, or `IMG_1.JPG.supplemental-metadata.json` in the recent exports,
+/// - the duplicates `IMG_1(1).JPG` have their sidecar named `IMG_1.JPG(1).json`,
+/// - the edited versions `IMG_1-edited.JPG` share the sidecar of the original,
+/// - the sidecar of `IMG_1.JPG` is sometimes named `IMG_1.json`.
+pub fn sidecar_names(media_name: &str) -> Vec<String> {
+    lazy_static! {
+        static ref DUPLICATE_RE: Regex = Regex::new(r"^(.*)(\(\d+\))(\.[^.]*)?$").unwrap();
+        static ref EDITED_RE: Regex = Regex::new(r"^(.*)-edited(\.[^.]*)?$").unwrap();
+    }
+
+    let mut names = Vec::new();
+
+    let mut push_names = |name: &str, index: &str| {
+        names.push(format!("{}.supplemental-metadata{}.json", name, index));
+        names.push(format!("{}{}.json", name, index));
+    };
+
+    if let Some(captures) = DUPLICATE_RE.captures(media_name) {
+        let original = format!(
+            "{}{}",
+            &captures[1],
+            captures.get(3).map_or("", |ext| ext.as_str())
+        );
+        push_names(&original, &captures[2]);
+    }
+
+    push_names(media_name, "");
+
+    if let Some(captures) = EDITED_RE.captures(media_name) {
+        let original = format!(
+            "{}{}",
+            &captures[1],
+            captures.get(2).map_or("", |ext| ext.as_str())
+        );
+        push_names(&original, "");
+    }
+
+    // without the media extension:
+    if let Some((stem, _extension)) = media_name.rsplit_once('.') {
+        names.push(format!("{}.json", stem));
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sidecar() {
+        let data = br#"{
+            "title": "IMG_1.JPG",
+            "description": "Tram 28",
+            "photoTakenTime": {"timestamp": "1683195072", "formatted": "4 May 2023, 10:11:12 UTC"},
+            "geoData": {"latitude": 0.0, "longitude": 0.0, "altitude": 0.0},
+            "geoDataExif": {"latitude": 38.7139, "longitude": -9.1334, "altitude": 52.0}
+        }"#;
+
+        assert_eq!(
+            parse_sidecar(data).unwrap(),
+            Sidecar {
+                taken_date: Some("2023-05-04 10:11:12".to_string()),
+                latitude: Some(38.7139),
+                longitude: Some(-9.1334),
+                altitude: Some(52.0),
+                caption: Some("Tram 28".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_and_edited_names() {
+        let jsons = ["IMG_1.JPG.json", "IMG_1.JPG(1).json"];
+
+        assert_eq!(
+            find_sidecar_name("IMG_1(1).JPG", jsons),
+            Some("IMG_1.JPG(1).json")
+        );
+        assert_eq!(
+            find_sidecar_name("IMG_1-edited.JPG", jsons),
+            Some("IMG_1.JPG.json")
+        );
+        assert_eq!(find_sidecar_name("IMG_2.JPG", jsons), None);
+    }
+
+    #[test]
+    fn test_truncated_names() {
+        let media = "Screenshot_20230504-101112_Some Long App Name.jpg";
+        let jsons = [
+            "Screenshot_20230504-101112_Other Long Name.jpg.json",
+            "Screenshot_20230504-101112_Some Long App Name..json",
+        ];
+
+        assert_eq!(
+            find_sidecar_name(media, jsons),
+            Some("Screenshot_20230504-101112_Some Long App Name..json")
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::exposure::Range;
+use chrono::Duration;
+
+/// Parses a duration like `5m`, `90s`, `2h`, `1h30m`, or an offset like `+01:00`, `-02:30:15`.
+/// A leading `-` makes it negative.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration {}, expected something like 5m, 1h30m, 90s or +01:00",
+            value
+        )
+    };
+
+    let trimmed = value.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    let seconds = if unsigned.contains(':') {
+        // HH:MM[:SS]
+        let parts = unsigned
+            .split(':')
+            .map(|part| part.parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|_| invalid())?;
+        match parts.as_slice() {
+            [hours, minutes] => hours * 3600 + minutes * 60,
+            [hours, minutes, seconds] => hours * 3600 + minutes * 60 + seconds,
+            _ => return Err(invalid()),
+        }
+    } else {
+        // a sequence of numbers with units:
+        let mut seconds = 0;
+        let mut number = String::new();
+        for c in unsigned.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let factor = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return Err(invalid()),
+            };
+            seconds += number.parse::<i64>().map_err(|_| invalid())? * factor;
+            number.clear();
+        }
+        if !number.is_empty() || unsigned.is_empty() {
+            return Err(invalid());
+        }
+        seconds
+    };
+
+    let duration = Duration::seconds(seconds);
+    Ok(if negative { -duration } else { duration })
+}
+
+/// Parses a file size like `500`, `200KB`, `1.5M` or `2GB`, in bytes (the units being powers of
+/// 1024, like in `photor stats`).
+pub fn parse_size(value: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "invalid size {}, expected something like 500KB, 1.5MB or 2GB",
+            value
+        )
+    };
+
+    let upper = value.trim().to_uppercase();
+    let number = upper.trim_end_matches('B');
+    let (number, factor) = match number.chars().last() {
+        Some('K') => (&number[..number.len() - 1], 1024.0),
+        Some('M') => (&number[..number.len() - 1], 1024.0 * 1024.0),
+        Some('G') => (&number[..number.len() - 1], 1024.0 * 1024.0 * 1024.0),
+        Some('T') => (
+            &number[..number.len() - 1],
+            1024.0 * 1024.0 * 1024.0 * 1024.0,
+        ),
+        _ => (number, 1.0),
+    };
+    let number = number.trim().parse::<f64>().map_err(|_| invalid())?;
+    if number < 0.0 {
+        return Err(invalid());
+    }
+    Ok((number * factor).round())
+}
+
+/// Parses a range of file sizes, like `1MB..10MB`, `..500KB` or `2GB..`.
+pub fn parse_size_range(value: &str) -> Result<Range, String> {
+    let parse_bound = |bound: &str| match bound.trim() {
+        "" => Ok(None),
+        bound => parse_size(bound).map(Some),
+    };
+
+    let range = match value.split_once("..") {
+        Some((min, max)) => Range {
+            min: parse_bound(min)?,
+            max: parse_bound(max)?,
+        },
+        None => {
+            let size = parse_size(value)?;
+            Range {
+                min: Some(size),
+                max: Some(size),
+            }
+        }
+    };
+
+    if let (Some(min), Some(max)) = (range.min, range.max) {
+        if min > max {
+            return Err(format!(
+                "invalid size range {}, {} is above {}",
+                value, min, max
+            ));
+        }
+    }
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("5m"), Ok(Duration::minutes(5)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+        assert_eq!(parse_duration("+01:00"), Ok(Duration::hours(1)));
+        assert_eq!(parse_duration("-02:30"), Ok(Duration::minutes(-150)));
+        assert!(parse_duration("5 minutes").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("500"), Ok(500.0));
+        assert_eq!(parse_size("2KB"), Ok(2048.0));
+        assert_eq!(parse_size("1.5m"), Ok(1572864.0));
+        assert!(parse_size("big").is_err());
+
+        assert_eq!(
+            parse_size_range("..1K"),
+            Ok(Range {
+                min: None,
+                max: Some(1024.0)
+            })
+        );
+        assert!(parse_size_range("2MB..1MB").is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Converts a duration as written by exiftool, like `12.34 s`, `0:01:23` or `83.2`, to seconds.
+pub fn parse_duration_seconds(value: &str) -> Option<f64> {
+    let value = value.trim();
+
+    if value.contains(':') {
+        // H:MM:SS, possibly with a fraction of seconds:
+        return value
+            .split(':')
+            .map(|part| part.trim().parse::<f64>().ok())
+            .try_fold(0.0, |total, part| Some(total * 60.0 + part?));
+    }
+
+    value
+        .trim_end_matches('s')
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0)
+}
+
+/// Converts a bitrate as written by exiftool, like `12.3 Mbps` or `850 kbps`, to bits per second.
+pub fn parse_bitrate(value: &str) -> Option<i64> {
+    lazy_static! {
+        static ref BITRATE_RE: Regex =
+            Regex::new(r"^\s*(\d+(?:\.\d+)?)\s*([kKmMgG]?)bps\s*$").unwrap();
+    }
+
+    let captures = match BITRATE_RE.captures(value) {
+        Some(captures) => captures,
+        // a plain number is in bits per second:
+        None => return value.trim().parse::<f64>().ok().map(|bps| bps as i64),
+    };
+    let number: f64 = captures[1].parse().ok()?;
+    let factor = match captures[2].to_ascii_lowercase().as_str() {
+        "k" => 1e3,
+        "m" => 1e6,
+        "g" => 1e9,
+        _ => 1.0,
+    };
+
+    Some((number * factor).round() as i64)
+}
+
+/// Returns the dimensions of a video as displayed, the width and height being swapped for the
+/// videos rotated by a quarter turn (portrait phone videos).
+pub fn display_dimensions(
+    width: Option<i64>,
+    height: Option<i64>,
+    rotation: Option<i64>,
+) -> (Option<i64>, Option<i64>) {
+    match rotation.map(|rotation| rotation.rem_euclid(180)) {
+        Some(90) => (height, width),
+        _ => (width, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration_seconds() {
+        assert_eq!(parse_duration_seconds("12.34 s"), Some(12.34));
+        assert_eq!(parse_duration_seconds("0:01:23"), Some(83.0));
+        assert_eq!(parse_duration_seconds("83.2"), Some(83.2));
+        assert_eq!(parse_duration_seconds("long"), None);
+    }
+
+    #[test]
+    fn test_parse_bitrate() {
+        assert_eq!(parse_bitrate("12.3 Mbps"), Some(12_300_000));
+        assert_eq!(parse_bitrate("850 kbps"), Some(850_000));
+        assert_eq!(parse_bitrate("64000"), Some(64_000));
+    }
+
+    #[test]
+    fn test_display_dimensions() {
+        assert_eq!(
+            display_dimensions(Some(1920), Some(1080), Some(90)),
+            (Some(1080), Some(1920))
+        );
+        assert_eq!(
+            display_dimensions(Some(1920), Some(1080), Some(180)),
+            (Some(1920), Some(1080))
+        );
+    }
+}